@@ -0,0 +1,290 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: (Apache-2.0 OR MIT)
+
+//! Validation of log filter directives at expansion time.
+//!
+//! We accept the union of the grammars understood by `env_logger` and
+//! `tracing-subscriber`'s `EnvFilter`, that is, comma separated
+//! directives of the form `target[span{field}]=level`, optionally
+//! followed by a `/regex` message filter.
+
+use std::collections::HashSet;
+
+use syn::parse::Parse;
+use syn::parse::ParseStream;
+use syn::punctuated::Punctuated;
+use syn::LitStr;
+use syn::MetaList;
+use syn::Path;
+use syn::Token;
+
+
+/// Level names known to both `env_logger` and `tracing-subscriber`.
+const LEVEL_NAMES: [&str; 6] = ["off", "error", "warn", "info", "debug", "trace"];
+
+
+/// Check whether `s` is the name of a level.
+fn is_level_name(s: &str) -> bool {
+  LEVEL_NAMES.iter().any(|name| name.eq_ignore_ascii_case(s))
+}
+
+/// Check whether `s` is a level, by name or, as supported by
+/// `tracing-subscriber`, by number.
+fn is_level(s: &str) -> bool {
+  is_level_name(s) || matches!(s, "0" | "1" | "2" | "3" | "4" | "5")
+}
+
+/// Check whether `s` is a valid target.
+fn is_target(s: &str) -> bool {
+  !s.is_empty()
+    && s
+      .chars()
+      .all(|c| c.is_alphanumeric() || matches!(c, '_' | ':' | '-' | '.'))
+}
+
+/// Split a list of directives at commas not enclosed in brackets,
+/// returning each directive along with its offset.
+fn split_directives(spec: &str) -> Vec<(usize, &str)> {
+  let mut directives = Vec::new();
+  let mut depth = 0usize;
+  let mut start = 0;
+
+  for (i, c) in spec.char_indices() {
+    match c {
+      '[' => depth += 1,
+      ']' => depth = depth.saturating_sub(1),
+      ',' if depth == 0 => {
+        directives.push((start, &spec[start..i]));
+        start = i + 1;
+      },
+      _ => (),
+    }
+  }
+  directives.push((start, &spec[start..]));
+  directives
+}
+
+/// Validate the span filter of a directive, i.e., the part inside of
+/// brackets, as in `[span{field=value}]`.
+fn validate_span(span: &str) -> Result<(), String> {
+  let (name, fields) = match span.split_once('{') {
+    Some((name, fields)) => {
+      let fields = fields
+        .strip_suffix('}')
+        .ok_or("unterminated field list, expected `}`")?;
+      (name, Some(fields))
+    },
+    None => (span, None),
+  };
+
+  if name.contains(['[', ']', '{', '}']) {
+    return Err(format!("invalid span name `{name}`"))
+  }
+  if let Some(fields) = fields {
+    if fields.split(',').any(|field| field.trim().is_empty()) {
+      return Err("empty field in field list".to_string())
+    }
+  }
+  Ok(())
+}
+
+/// Validate a single (trimmed) directive.
+fn validate_directive(directive: &str) -> Result<(), String> {
+  // Separate the span filter, if any, first, as it may contain `=`
+  // itself.
+  let (target, span, rest) = match directive.find('[') {
+    Some(start) => {
+      let end = directive
+        .rfind(']')
+        .filter(|end| *end > start)
+        .ok_or("unterminated span filter, expected `]`")?;
+      (
+        &directive[..start],
+        Some(&directive[start + 1..end]),
+        &directive[end + 1..],
+      )
+    },
+    None => match directive.find('=') {
+      Some(idx) => (&directive[..idx], None, &directive[idx..]),
+      None => (directive, None, ""),
+    },
+  };
+  let target = target.trim();
+  let level = match rest.trim() {
+    "" => None,
+    rest => Some(
+      rest
+        .strip_prefix('=')
+        .ok_or("unexpected characters after `]`")?
+        .trim(),
+    ),
+  };
+
+  if let Some(span) = span {
+    validate_span(span)?;
+  }
+
+  if let Some(level) = level {
+    if level.contains('=') {
+      return Err("more than one `=`".to_string())
+    }
+    if !level.is_empty() && !is_level(level) {
+      return Err(format!(
+        "unknown level `{level}`, expected one of {} (or 0-5)",
+        LEVEL_NAMES.join(", ")
+      ))
+    }
+  } else if span.is_none() && is_level(target) {
+    return Ok(())
+  }
+
+  if target.is_empty() {
+    if span.is_none() {
+      return Err("missing target".to_string())
+    }
+  } else if !is_target(target) {
+    return Err(format!("invalid target `{target}`"))
+  }
+  Ok(())
+}
+
+
+/// Parse and validate a filter specification from a string literal,
+/// returning it unchanged on success.
+///
+/// Errors point at the literal and highlight the offending directive
+/// within it.
+pub(crate) fn parse_filter(lit: &LitStr) -> syn::Result<String> {
+  let string = lit.value();
+  // `env_logger` supports filtering messages by regular expression via
+  // a trailing `/regex`, which we cannot validate here.
+  let spec = match string.split_once('/') {
+    Some((spec, _regex)) => spec,
+    None => string.as_str(),
+  };
+
+  for (offset, directive) in split_directives(spec) {
+    let leading = directive.len() - directive.trim_start().len();
+    let offset = offset + leading;
+    let directive = directive.trim();
+    if directive.is_empty() {
+      continue
+    }
+
+    if let Err(reason) = validate_directive(directive) {
+      return Err(syn::Error::new_spanned(
+        lit,
+        format!(
+          "Invalid filter directive `{directive}`: {reason}.\n  {string}\n  {}{}",
+          " ".repeat(string[..offset].chars().count()),
+          "^".repeat(directive.chars().count()),
+        ),
+      ))
+    }
+  }
+  Ok(string)
+}
+
+
+/// The key of an entry of the structured `filter(...)` form.
+enum Key {
+  /// The `default` key, setting the level for all targets.
+  Default,
+  /// A target, specified as path or string literal.
+  Target(String),
+}
+
+/// An entry of the structured `filter(...)` form, such as
+/// `my_crate = "debug"` or `"my_crate::db" = "trace"`.
+struct Entry {
+  /// The entry's key.
+  key: Key,
+  /// The level literal.
+  level: LitStr,
+}
+
+impl Parse for Entry {
+  fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+    let key = if input.peek(LitStr) {
+      let target = input.parse::<LitStr>()?;
+      if !is_target(&target.value()) {
+        return Err(syn::Error::new_spanned(
+          &target,
+          format!("Invalid target `{}`.", target.value()),
+        ))
+      }
+      Key::Target(target.value())
+    } else {
+      let path = input.call(Path::parse_mod_style)?;
+      if path.is_ident("default") {
+        Key::Default
+      } else {
+        let target = path
+          .segments
+          .iter()
+          .map(|segment| segment.ident.to_string())
+          .collect::<Vec<_>>()
+          .join("::");
+        Key::Target(target)
+      }
+    };
+    let _eq = input.parse::<Token![=]>()?;
+    let level = input.parse::<LitStr>()?;
+    // Only levels by name are understood by both backends.
+    if !is_level_name(&level.value()) {
+      return Err(syn::Error::new_spanned(
+        &level,
+        format!(
+          "Invalid level `{}`, expected one of {}.",
+          level.value(),
+          LEVEL_NAMES.join(", ")
+        ),
+      ))
+    }
+    Ok(Self { key, level })
+  }
+}
+
+/// Parse the structured `filter(default = "info", my_crate = "debug")`
+/// form into the equivalent filter specification.
+pub(crate) fn parse_structured(list: &MetaList) -> syn::Result<String> {
+  let entries = list.parse_args_with(Punctuated::<Entry, Token![,]>::parse_terminated)?;
+  if entries.is_empty() {
+    return Err(syn::Error::new_spanned(
+      list,
+      "Expected at least one `target = \"level\"` pair, e.g. 'filter(default = \"info\", my_crate = \"debug\")'.",
+    ))
+  }
+
+  let mut seen = HashSet::new();
+  let mut default = None;
+  let mut directives = Vec::new();
+  for Entry { key, level } in entries {
+    let level_str = level.value().to_ascii_lowercase();
+    match key {
+      Key::Default if default.is_some() => {
+        return Err(syn::Error::new_spanned(
+          level,
+          "Duplicate filter target `default`.",
+        ))
+      },
+      Key::Default => default = Some(level_str),
+      Key::Target(target) if !seen.insert(target.clone()) => {
+        return Err(syn::Error::new_spanned(
+          level,
+          format!("Duplicate filter target `{target}`."),
+        ))
+      },
+      Key::Target(target) => directives.push(format!("{target}={level_str}")),
+    }
+  }
+
+  // The global level goes first, as is customary.
+  Ok(
+    default
+      .into_iter()
+      .chain(directives)
+      .collect::<Vec<_>>()
+      .join(","),
+  )
+}
@@ -3,24 +3,92 @@
 
 //! Core logic for the `test-log` procedural macro.
 
+mod filter;
+mod manifest;
+
 use std::borrow::Cow;
+use std::env;
+use std::mem;
+use std::path::Path as FsPath;
+use std::path::PathBuf;
 
+use proc_macro2::Ident;
+use proc_macro2::Span;
 use proc_macro2::TokenStream as Tokens;
+use proc_macro2::TokenTree;
 
 use quote::quote;
+use quote::ToTokens as _;
 
-use syn::parse::Parse;
+use syn::parse::Parser as _;
+use syn::parse_quote;
+use syn::punctuated::Punctuated;
 use syn::Attribute;
+use syn::Block;
 use syn::Expr;
+use syn::ExprLit;
+use syn::Item;
 use syn::ItemFn;
+use syn::ItemMod;
 use syn::Lit;
+use syn::LitStr;
 use syn::Meta;
+use syn::MetaList;
+use syn::Path;
+use syn::ReturnType;
+use syn::Signature;
+use syn::Token;
+
+
+/// Parse the arguments of the main `#[test_log::test(...)]` attribute
+/// into `attribute_args`, returning the inner test attribute, if any.
+///
+/// Arguments of the `key = value` form (as well as structured ones,
+/// such as `expect_log(...)`) are treated as configuration, while the
+/// remaining one, if present, is the inner test attribute.
+fn parse_main_attr(
+  attr: Tokens,
+  attribute_args: &mut AttributeArgs,
+) -> syn::Result<Option<Tokens>> {
+  if attr.is_empty() {
+    return Ok(None)
+  }
+
+  // If the arguments are not a list of `Meta`s to begin with, they
+  // can only be meant as an inner test attribute.
+  let metas = match Punctuated::<Meta, Token![,]>::parse_terminated.parse2(attr.clone()) {
+    Ok(metas) => metas,
+    Err(_) => return Ok(Some(attr)),
+  };
+
+  let mut inner_test = None;
+  for meta in metas {
+    let is_config = match &meta {
+      Meta::NameValue(..) => true,
+      Meta::List(list) => AttributeArgs::is_structured(&list.path),
+      Meta::Path(..) => false,
+    };
 
+    if is_config {
+      attribute_args.parse_meta(meta)?;
+    } else if inner_test.is_some() {
+      return Err(syn::Error::new_spanned(
+        &meta,
+        "Expected at most one inner test attribute.",
+      ))
+    } else {
+      inner_test = Some(meta.into_token_stream());
+    }
+  }
+  Ok(inner_test)
+}
 
 /// Parse `#[test_log(...)]` attributes from a function's attribute
-/// list, separating them from other attributes.
-fn parse_attrs(attrs: Vec<Attribute>) -> syn::Result<(AttributeArgs, Vec<Attribute>)> {
-  let mut attribute_args = AttributeArgs::default();
+/// list into `attribute_args`, returning the remaining attributes.
+fn parse_attrs(
+  attrs: Vec<Attribute>,
+  attribute_args: &mut AttributeArgs,
+) -> syn::Result<Vec<Attribute>> {
   if cfg!(feature = "unstable") {
     let mut ignored_attrs = vec![];
     for attr in attrs {
@@ -31,9 +99,9 @@ fn parse_attrs(attrs: Vec<Attribute>) -> syn::Result<(AttributeArgs, Vec<Attribu
       }
     }
 
-    Ok((attribute_args, ignored_attrs))
+    Ok(ignored_attrs)
   } else {
-    Ok((attribute_args, attrs))
+    Ok(attrs)
   }
 }
 
@@ -69,8 +137,191 @@ fn is_test_attribute(attr: &Attribute) -> bool {
 }
 
 
+/// Retrieve the path to the manifest of the crate being compiled, as
+/// found via `CARGO_MANIFEST_DIR`.
+fn manifest_path() -> Option<PathBuf> {
+  env::var_os("CARGO_MANIFEST_DIR")
+    .map(|dir| PathBuf::from(dir).join("Cargo.toml"))
+    .filter(|manifest| manifest.is_file())
+}
+
+/// Read the crate-wide defaults from the provided manifest, if any,
+/// returning them along with the code making the test depend on the
+/// manifest.
+fn parse_manifest(manifest: Option<&FsPath>) -> syn::Result<(AttributeArgs, Tokens)> {
+  let mut defaults = AttributeArgs::default();
+  let dependency = match manifest {
+    Some(manifest) => {
+      let used = manifest::parse_defaults(manifest, |meta| defaults.parse_meta(meta))?;
+      expand_manifest_dependency(manifest, used)
+    },
+    None => quote! {},
+  };
+  Ok((defaults, dependency))
+}
+
+/// Third-party attributes known to define a test, by path.
+const KNOWN_TEST_ATTRIBUTES: [&[&str]; 8] = [
+  &["tokio", "test"],
+  &["async_std", "test"],
+  &["actix_rt", "test"],
+  &["rstest"],
+  &["rstest", "rstest"],
+  &["test_case"],
+  &["test_case", "test_case"],
+  &["test_case", "test_matrix"],
+];
+
+
+/// Check whether the given attribute is a known third-party test
+/// attribute, such as `#[tokio::test]` or `#[rstest]`.
+fn is_known_test_attribute(attr: &Attribute) -> bool {
+  let path = attr.path();
+  KNOWN_TEST_ATTRIBUTES.iter().any(|segments| {
+    path.segments.len() == segments.len()
+      && path
+        .segments
+        .iter()
+        .zip(segments.iter())
+        .all(|(segment, name)| segment.ident == name)
+  })
+}
+
+/// Check whether the given attribute defines a test, be it the built-in
+/// `#[test]` or a known third-party one.
+fn is_any_test_attribute(attr: &Attribute) -> bool {
+  is_test_attribute(attr) || is_known_test_attribute(attr)
+}
+
+/// Check whether the given attribute is `#[test_log::test]`, with the
+/// crate referred to as `test_log` or via the provided path.
+fn is_test_log_attribute(attr: &Attribute, krate: Option<&Path>) -> bool {
+  fn idents(path: &Path) -> Vec<String> {
+    path
+      .segments
+      .iter()
+      .map(|segment| segment.ident.to_string())
+      .collect()
+  }
+
+  match idents(attr.path()).split_last() {
+    Some((test, path)) if test == "test" => {
+      path == ["test_log"] || krate.is_some_and(|krate| idents(krate) == path)
+    },
+    _ => false,
+  }
+}
+
+
 /// Main expansion logic for `#[test_log::test]`.
+///
+/// Crate-wide defaults are read from the manifest of the crate being
+/// compiled, as found via `CARGO_MANIFEST_DIR`.
 pub fn try_test(attr: Tokens, input: ItemFn) -> syn::Result<Tokens> {
+  try_test_with_manifest(attr, input, manifest_path().as_deref())
+}
+
+/// Expansion logic for `#[test_log::test]`, with crate-wide defaults
+/// read from the `[package.metadata.test-log]` table of the provided
+/// manifest, if any.
+pub fn try_test_with_manifest(
+  attr: Tokens,
+  input: ItemFn,
+  manifest: Option<&FsPath>,
+) -> syn::Result<Tokens> {
+  let (defaults, manifest) = parse_manifest(manifest)?;
+  expand_test(attr, input, defaults, &manifest)
+}
+
+/// Main expansion logic for `#[test_log::test_module]`.
+///
+/// Crate-wide defaults are read from the manifest of the crate being
+/// compiled, as found via `CARGO_MANIFEST_DIR`.
+pub fn try_test_module(attr: Tokens, input: ItemMod) -> syn::Result<Tokens> {
+  try_test_module_with_manifest(attr, input, manifest_path().as_deref())
+}
+
+/// Expansion logic for `#[test_log::test_module]`, with crate-wide
+/// defaults read from the `[package.metadata.test-log]` table of the
+/// provided manifest, if any.
+///
+/// Every function of the module carrying a test attribute is expanded
+/// as if annotated with `#[test_log::test]`, inheriting the module's
+/// configuration.
+pub fn try_test_module_with_manifest(
+  attr: Tokens,
+  mut input: ItemMod,
+  manifest: Option<&FsPath>,
+) -> syn::Result<Tokens> {
+  let mut module_args = AttributeArgs::default();
+  if let Some(inner) = parse_main_attr(attr, &mut module_args)? {
+    return Err(syn::Error::new_spanned(
+      inner,
+      "Expected only configuration, e.g. 'filter = \"debug\"'.",
+    ))
+  }
+  input.attrs = parse_attrs(mem::take(&mut input.attrs), &mut module_args)?;
+
+  let (defaults, manifest) = parse_manifest(manifest)?;
+  module_args.with_defaults(defaults);
+  // Tests may refer to our attribute via the path to the crate, be it
+  // provided explicitly or due to a renamed dependency.
+  let krate = module_args
+    .krate
+    .clone()
+    .and_then(|krate| syn::parse2::<Path>(krate).ok());
+
+  let items = match &mut input.content {
+    Some((_brace, items)) => items,
+    None => {
+      return Err(syn::Error::new_spanned(
+        &input,
+        "Expected an inline module, e.g. 'mod tests { ... }'.",
+      ))
+    },
+  };
+
+  for item in items.iter_mut() {
+    let function = match item {
+      Item::Fn(function) => function,
+      _ => continue,
+    };
+
+    // A function already annotated with our own attribute is expanded
+    // by us right away, with its arguments taking precedence.
+    let attr = match function
+      .attrs
+      .iter()
+      .position(|attr| is_test_log_attribute(attr, krate.as_ref()))
+    {
+      Some(idx) => match function.attrs.remove(idx).meta {
+        Meta::List(list) => list.tokens,
+        _ => Tokens::new(),
+      },
+      None if function.attrs.iter().any(is_any_test_attribute) => Tokens::new(),
+      None => continue,
+    };
+
+    let function = mem::replace(function, parse_quote! { fn placeholder() {} });
+    let expanded = expand_test(attr, function, module_args.clone(), &Tokens::new())?;
+    *item = Item::Verbatim(expanded);
+  }
+  // The dependency on the manifest is shared by all tests of the
+  // module.
+  if !manifest.is_empty() {
+    let () = items.push(Item::Verbatim(manifest));
+  }
+  Ok(input.into_token_stream())
+}
+
+/// Expand a single test function, using `defaults` for all
+/// configuration not provided by the test itself.
+fn expand_test(
+  attr: Tokens,
+  input: ItemFn,
+  defaults: AttributeArgs,
+  manifest: &Tokens,
+) -> syn::Result<Tokens> {
   let ItemFn {
     attrs,
     vis,
@@ -78,20 +329,24 @@ pub fn try_test(attr: Tokens, input: ItemFn) -> syn::Result<Tokens> {
     block,
   } = input;
 
-  let (attribute_args, ignored_attrs) = parse_attrs(attrs)?;
-  let logging_init = expand_logging_init(&attribute_args);
-  let tracing_init = expand_tracing_init(&attribute_args);
+  let mut attribute_args = AttributeArgs::default();
+  let inner_test = parse_main_attr(attr, &mut attribute_args)?;
+  let ignored_attrs = parse_attrs(attrs, &mut attribute_args)?;
+  attribute_args.with_defaults(defaults);
+  let config = expand_config(&attribute_args, &sig.ident);
+  let krate = expand_crate(attribute_args.krate.as_ref());
+  let body = expand_body(&sig, &block, &krate);
 
-  let (inner_test, generated_test) = if attr.is_empty() {
-    let has_test = ignored_attrs.iter().any(is_test_attribute);
+  let (inner_test, generated_test) = if let Some(inner_test) = inner_test {
+    (quote! { #[#inner_test] }, quote! {})
+  } else {
+    let has_test = ignored_attrs.iter().any(is_any_test_attribute);
     let generated_test = if has_test {
       quote! {}
     } else {
       quote! { #[::core::prelude::v1::test]}
     };
     (quote! {}, generated_test)
-  } else {
-    (quote! { #[#attr] }, quote! {})
   };
 
   let result = quote! {
@@ -99,49 +354,503 @@ pub fn try_test(attr: Tokens, input: ItemFn) -> syn::Result<Tokens> {
     #(#ignored_attrs)*
     #generated_test
     #vis #sig {
-      // We put all initialization code into a separate module here in
-      // order to prevent potential ambiguities that could result in
-      // compilation errors. E.g., client code could use traits that
-      // could have methods that interfere with ones we use as part of
-      // initialization; with a `Foo` trait that is implemented for T
-      // and that contains a `map` (or similarly common named) method
-      // that could cause an ambiguity with `Iterator::map`, for
-      // example.
-      // The alternative would be to use fully qualified call syntax in
-      // all initialization code, but that's much harder to control.
-      mod init {
-        pub fn init() {
-          #logging_init
-          #tracing_init
-        }
-      }
-
-      init::init();
+      // Each test gets its own scope, so that its configuration applies
+      // regardless of which other tests ran (or are running) in the
+      // same process. All setup happens in the runtime, based on a
+      // constant configuration, keeping the generated code small.
+      let __internal_scope_guard = {
+        #manifest
+        const __INTERNAL_CONFIG: #krate::__private::Config<'static> = #config;
+        #krate::__private::init(&__INTERNAL_CONFIG)
+      };
+      let __internal_result = #body;
+      let __internal_failed = {
+        #[allow(unused_imports)]
+        use #krate::__private::AnyOutcome as _;
+        #[allow(unused_imports)]
+        use #krate::__private::ResultOutcome as _;
 
-      #block
+        (&#krate::__private::Outcome(&__internal_result)).is_failure()
+      };
+      __internal_scope_guard.finish(__internal_failed);
+      __internal_result
     }
   };
   Ok(result)
 }
 
 
+/// Check whether a type mentions `impl Trait` anywhere.
+fn contains_impl_trait(tokens: Tokens) -> bool {
+  tokens.into_iter().any(|token| match token {
+    TokenTree::Ident(ident) => ident == "impl",
+    TokenTree::Group(group) => contains_impl_trait(group.stream()),
+    _ => false,
+  })
+}
+
+/// Expand the evaluation of the test function's body.
+///
+/// The body is evaluated in a closure (or `async` block), so that an
+/// early `return` from it does not bypass the work we have to do once
+/// the test is done. While evaluated, the test's scope is entered:
+/// synchronous bodies enter it on the current thread, while `async`
+/// ones enter it every time the body's future is polled, irrespective
+/// of the thread doing so.
+fn expand_body(sig: &Signature, block: &Block, krate: &Tokens) -> Tokens {
+  if sig.asyncness.is_some() {
+    let body = expand_async_body(sig, block, krate);
+    quote! {
+      __internal_scope_guard.scoped(async move { #body }).await
+    }
+  } else {
+    let body = match &sig.output {
+      ReturnType::Type(_, ty) if contains_impl_trait(ty.to_token_stream()) => {
+        quote! { (move || #block)() }
+      },
+      ReturnType::Type(_, ty) => quote! { (move || -> #ty #block)() },
+      ReturnType::Default => quote! { (move || -> () #block)() },
+    };
+    let body = expand_sync_body(sig, body, krate);
+    quote! {
+      {
+        let __internal_entered = __internal_scope_guard.enter();
+        #body
+      }
+    }
+  }
+}
+
+/// Expand the creation of the span wrapping the test's body, which is
+/// named after the test.
+#[cfg(feature = "trace")]
+fn expand_span(sig: &Signature, krate: &Tokens) -> Tokens {
+  let ident = &sig.ident;
+  quote! {
+    #krate::__private::tracing::info_span!(
+      ::core::concat!(::core::module_path!(), "::", ::core::stringify!(#ident))
+    )
+  }
+}
+
+/// Expand the evaluation of a synchronous body.
+#[cfg(feature = "trace")]
+fn expand_sync_body(sig: &Signature, body: Tokens, krate: &Tokens) -> Tokens {
+  let span = expand_span(sig, krate);
+  quote! {
+    let __internal_span = #span;
+    let __internal_span_entered = __internal_span.enter();
+    #body
+  }
+}
+
+#[cfg(not(feature = "trace"))]
+fn expand_sync_body(_sig: &Signature, body: Tokens, _krate: &Tokens) -> Tokens {
+  body
+}
+
+/// Expand the evaluation of an asynchronous body.
+///
+/// The span is attached to the body's future, so that it is entered
+/// whenever the future is polled.
+#[cfg(feature = "trace")]
+fn expand_async_body(sig: &Signature, block: &Block, krate: &Tokens) -> Tokens {
+  let span = expand_span(sig, krate);
+  quote! {
+    #krate::__private::tracing::Instrument::instrument(async move #block, #span).await
+  }
+}
+
+#[cfg(not(feature = "trace"))]
+fn expand_async_body(_sig: &Signature, block: &Block, _krate: &Tokens) -> Tokens {
+  quote! { async move #block.await }
+}
+
+
+/// The log levels known to both the `log` and `tracing` crates.
+const LEVELS: [&str; 5] = ["error", "warn", "info", "debug", "trace"];
+
+
+/// Parse a log level from a string literal, returning its lower case
+/// name.
+fn parse_level(lit: &LitStr) -> syn::Result<String> {
+  let string = lit.value();
+  let level = string.to_ascii_lowercase();
+  if !LEVELS.contains(&level.as_str()) {
+    return Err(syn::Error::new_spanned(
+      lit,
+      format!(
+        "Invalid level `{string}`, expected one of {}.",
+        LEVELS.join(", ")
+      ),
+    ))
+  }
+  Ok(level)
+}
+
+
+/// An expectation on the events emitted by a test, as specified via
+/// `#[test_log(expect_log(...))]`.
+#[derive(Clone, Debug, Default)]
+struct ExpectLog {
+  /// The (lower case) level the event has to be emitted at.
+  level: Option<String>,
+  /// The target (or a parent thereof) of the event.
+  target: Option<String>,
+  /// A string that the event's message has to contain.
+  contains: Option<String>,
+}
+
+impl ExpectLog {
+  /// Parse the arguments of an `expect_log(...)` attribute.
+  fn parse(list: &MetaList) -> syn::Result<Self> {
+    let mut expect = Self::default();
+    list.parse_nested_meta(|meta| {
+      let arg_ref = if meta.path.is_ident("level") {
+        &mut expect.level
+      } else if meta.path.is_ident("target") {
+        &mut expect.target
+      } else if meta.path.is_ident("contains") {
+        &mut expect.contains
+      } else {
+        return Err(meta.error(
+          "Unrecognized expect_log argument, expected one of `level`, `target`, or `contains`.",
+        ))
+      };
+
+      if arg_ref.is_some() {
+        return Err(meta.error("Duplicate expect_log argument."))
+      }
+
+      let value = meta.value()?.parse::<LitStr>()?;
+      if meta.path.is_ident("level") {
+        *arg_ref = Some(parse_level(&value)?);
+      } else {
+        *arg_ref = Some(value.value());
+      }
+      Ok(())
+    })?;
+
+    if expect.level.is_none() && expect.target.is_none() && expect.contains.is_none() {
+      return Err(syn::Error::new_spanned(
+        list,
+        "Expected at least one of `level`, `target`, or `contains`, e.g. 'expect_log(contains = \"retrying\")'.",
+      ))
+    }
+    Ok(expect)
+  }
+}
+
+
+/// The synthesized span lifecycle events supported by `span_events`.
+const SPAN_EVENTS: [&str; 6] = ["new", "enter", "exit", "close", "active", "full"];
+
+
+/// Parse a comma separated list of span events from a string literal,
+/// returning it in normalized form.
+fn parse_span_events(lit: &LitStr) -> syn::Result<String> {
+  let string = lit.value();
+  let events = string
+    .split(',')
+    .map(|event| {
+      let event = event.trim().to_ascii_lowercase();
+      if SPAN_EVENTS.contains(&event.as_str()) {
+        Ok(event)
+      } else {
+        Err(syn::Error::new_spanned(
+          lit,
+          format!(
+            "Invalid span event `{event}`, expected a comma separated list of {}.",
+            SPAN_EVENTS.join(", ")
+          ),
+        ))
+      }
+    })
+    .collect::<syn::Result<Vec<_>>>()?;
+  Ok(events.join(","))
+}
+
+
+/// Retrieve the string literal that `value` is, if any.
+fn expect_lit_str(value: &Expr) -> syn::Result<&LitStr> {
+  match value {
+    Expr::Lit(ExprLit {
+      lit: Lit::Str(lit_str),
+      ..
+    }) => Ok(lit_str),
+    value => Err(syn::Error::new_spanned(
+      value,
+      "Failed to parse value, expected a string",
+    )),
+  }
+}
+
+
+/// The supported policies for combining `RUST_LOG` with the filter.
+const ENV_POLICIES: [&str; 3] = ["override", "merge", "ignore"];
+
+
+/// Parse the name of an environment variable from a string literal.
+fn parse_env_var(lit: &LitStr) -> syn::Result<String> {
+  let name = lit.value();
+  let mut chars = name.chars();
+  let valid = chars
+    .next()
+    .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+    && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+  if !valid {
+    return Err(syn::Error::new_spanned(
+      lit,
+      format!(
+        "Invalid environment variable name `{name}`, expected ASCII letters, digits, and underscores."
+      ),
+    ))
+  }
+  Ok(name)
+}
+
+/// Parse a policy for combining `RUST_LOG` with the filter from a string
+/// literal.
+fn parse_env_policy(lit: &LitStr) -> syn::Result<String> {
+  let policy = lit.value();
+  if !ENV_POLICIES.contains(&policy.as_str()) {
+    return Err(syn::Error::new_spanned(
+      lit,
+      format!(
+        "Invalid environment policy `{policy}`, expected one of {}.",
+        ENV_POLICIES.join(", ")
+      ),
+    ))
+  }
+  Ok(policy)
+}
+
+
+/// The configuration of the environment variable providing filter
+/// directives, as specified via `env = "..."` or
+/// `env(var = "...", policy = "...")`.
+#[derive(Clone, Debug, Default)]
+struct Env {
+  /// The name of the variable to use instead of `RUST_LOG`.
+  var: Option<String>,
+  /// The policy for combining the variable with the filter.
+  policy: Option<String>,
+}
+
+impl Env {
+  /// Parse the value of `env = "..."`, which is either a policy or the
+  /// name of a variable.
+  ///
+  /// Only the names of policies are interpreted as such, while
+  /// everything else is taken for a variable name.
+  fn parse_value(lit: &LitStr) -> syn::Result<Self> {
+    if ENV_POLICIES.contains(&lit.value().as_str()) {
+      Ok(Self {
+        policy: Some(parse_env_policy(lit)?),
+        ..Default::default()
+      })
+    } else {
+      Ok(Self {
+        var: Some(parse_env_var(lit)?),
+        ..Default::default()
+      })
+    }
+  }
+
+  /// Parse the arguments of an `env(...)` attribute.
+  fn parse(list: &MetaList) -> syn::Result<Self> {
+    let mut env = Self::default();
+    list.parse_nested_meta(|meta| {
+      let arg_ref = if meta.path.is_ident("var") {
+        &mut env.var
+      } else if meta.path.is_ident("policy") {
+        &mut env.policy
+      } else {
+        return Err(meta.error("Unrecognized env argument, expected one of `var` or `policy`."))
+      };
+
+      if arg_ref.is_some() {
+        return Err(meta.error("Duplicate env argument."))
+      }
+
+      let value = meta.value()?.parse::<LitStr>()?;
+      if meta.path.is_ident("var") {
+        *arg_ref = Some(parse_env_var(&value)?);
+      } else {
+        *arg_ref = Some(parse_env_policy(&value)?);
+      }
+      Ok(())
+    })?;
+
+    if env.var.is_none() && env.policy.is_none() {
+      return Err(syn::Error::new_spanned(
+        list,
+        "Expected at least one of `var` or `policy`, e.g. 'env(var = \"MY_CRATE_LOG\", policy = \"merge\")'.",
+      ))
+    }
+    Ok(env)
+  }
+}
+
+
+/// The supported modes for `capture`.
+const CAPTURE_MODES: [&str; 2] = ["off", "on-failure"];
+
+/// The supported modes for `log_files`.
+const LOG_FILES_MODES: [&str; 2] = ["off", "on"];
+
+/// The supported choices for coloring output.
+const COLORS: [&str; 3] = ["auto", "always", "never"];
+
+/// The supported output formats.
+const FORMATS: [&str; 4] = ["full", "compact", "pretty", "json"];
+
+
 /// Parsed `#[test_log(...)]` attributes.
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default)]
 struct AttributeArgs {
-  /// The default log filter directive (e.g., `"debug"`).
+  /// The default log filter directive (e.g., `"debug"`), specified via
+  /// `default_log_filter` or its alias `filter`.
   default_log_filter: Option<Cow<'static, str>>,
+  /// Expectations on the events emitted by the test.
+  expect_log: Vec<ExpectLog>,
+  /// The (lower case) level at or above which events cause the test to
+  /// fail.
+  deny_level: Option<Cow<'static, str>>,
+  /// Comma separated targets exempt from `deny_level`.
+  deny_exempt: Option<Cow<'static, str>>,
+  /// The mode determining when the test's output is emitted.
+  capture: Option<Cow<'static, str>>,
+  /// Comma separated synthesized span lifecycle events to emit.
+  span_events: Option<Cow<'static, str>>,
+  /// The configuration of the environment variable providing filter
+  /// directives.
+  env: Option<Env>,
+  /// The name of the variable to use instead of `RUST_LOG_SPAN_EVENTS`.
+  span_events_env: Option<Cow<'static, str>>,
+  /// Whether to color output.
+  color: Option<Cow<'static, str>>,
+  /// The format in which output is emitted.
+  format: Option<Cow<'static, str>>,
+  /// Whether to write output to a per-test log file as well.
+  log_files: Option<Cow<'static, str>>,
+  /// The path to the `test-log` crate, specified via `crate`.
+  krate: Option<Tokens>,
 }
 
 impl AttributeArgs {
-  /// Try to parse a single `#[test_log(...)]` attribute.
+  /// Fill in the arguments not set from `defaults`, e.g., as provided
+  /// for the module or the crate as a whole.
+  fn with_defaults(&mut self, defaults: Self) {
+    let Self {
+      default_log_filter,
+      expect_log,
+      deny_level,
+      deny_exempt,
+      capture,
+      span_events,
+      env,
+      span_events_env,
+      color,
+      format,
+      log_files,
+      krate,
+    } = defaults;
+
+    if self.default_log_filter.is_none() {
+      self.default_log_filter = default_log_filter;
+    }
+    if self.expect_log.is_empty() {
+      self.expect_log = expect_log;
+    }
+    if self.deny_level.is_none() {
+      self.deny_level = deny_level;
+    }
+    if self.deny_exempt.is_none() {
+      self.deny_exempt = deny_exempt;
+    }
+    if self.capture.is_none() {
+      self.capture = capture;
+    }
+    if self.span_events.is_none() {
+      self.span_events = span_events;
+    }
+    if self.span_events_env.is_none() {
+      self.span_events_env = span_events_env;
+    }
+    if self.color.is_none() {
+      self.color = color;
+    }
+    if self.format.is_none() {
+      self.format = format;
+    }
+    if self.log_files.is_none() {
+      self.log_files = log_files;
+    }
+    if self.krate.is_none() {
+      self.krate = krate;
+    }
+    self.env = match (self.env.take(), env) {
+      (Some(test), Some(defaults)) => Some(Env {
+        var: test.var.or(defaults.var),
+        policy: test.policy.or(defaults.policy),
+      }),
+      (test, defaults) => test.or(defaults),
+    };
+  }
+
+  /// Try to parse a single `#[test_log(...)]` attribute, which may
+  /// contain a comma separated list of arguments.
   fn try_parse_attr_single(&mut self, attr: &Attribute) -> syn::Result<bool> {
     if !attr.path().is_ident("test_log") {
       return Ok(false)
     }
 
-    let nested_meta = attr.parse_args_with(Meta::parse)?;
+    let nested_metas = attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+    for nested_meta in nested_metas {
+      self.parse_meta(nested_meta)?;
+    }
+    Ok(true)
+  }
+
+  /// Check whether `path` names an argument of the `name(...)` form.
+  fn is_structured(path: &Path) -> bool {
+    path.is_ident("expect_log") || path.is_ident("filter") || path.is_ident("env")
+  }
+
+  /// Parse a single argument of a `#[test_log(...)]` attribute.
+  fn parse_meta(&mut self, nested_meta: Meta) -> syn::Result<()> {
     let name_value = if let Meta::NameValue(name_value) = nested_meta {
       name_value
+    } else if let Meta::List(list) = &nested_meta {
+      if list.path.is_ident("expect_log") {
+        self.expect_log.push(ExpectLog::parse(list)?);
+        return Ok(())
+      }
+      if list.path.is_ident("filter") {
+        if self.default_log_filter.is_some() {
+          return Err(syn::Error::new_spanned(
+            &list.path,
+            "Duplicate attribute `filter`.",
+          ))
+        }
+        self.default_log_filter = Some(Cow::from(filter::parse_structured(list)?));
+        return Ok(())
+      }
+      if list.path.is_ident("env") {
+        if self.env.is_some() {
+          return Err(syn::Error::new_spanned(
+            &list.path,
+            "Duplicate attribute `env`.",
+          ))
+        }
+        self.env = Some(Env::parse(list)?);
+        return Ok(())
+      }
+      return Err(syn::Error::new_spanned(
+        &list.path,
+        "Unrecognized attribute, see documentation for details.",
+      ))
     } else {
       return Err(syn::Error::new_spanned(
         &nested_meta,
@@ -158,8 +867,53 @@ impl AttributeArgs {
       ))
     };
 
-    let arg_ref = if ident == "default_log_filter" {
+    if ident == "env" {
+      if self.env.is_some() {
+        return Err(syn::Error::new_spanned(
+          &name_value.path,
+          "Duplicate attribute `env`.",
+        ))
+      }
+      self.env = Some(Env::parse_value(expect_lit_str(&name_value.value)?)?);
+      return Ok(())
+    }
+
+    if ident == "crate" {
+      if self.krate.is_some() {
+        return Err(syn::Error::new_spanned(
+          &name_value.path,
+          "Duplicate attribute `crate`.",
+        ))
+      }
+      let lit_str = expect_lit_str(&name_value.value)?;
+      let path = lit_str.parse::<Path>().map_err(|_err| {
+        syn::Error::new_spanned(
+          lit_str,
+          format!("Invalid crate path `{}`.", lit_str.value()),
+        )
+      })?;
+      self.krate = Some(path.into_token_stream());
+      return Ok(())
+    }
+
+    let arg_ref = if ident == "default_log_filter" || ident == "filter" {
       &mut self.default_log_filter
+    } else if ident == "deny_level" {
+      &mut self.deny_level
+    } else if ident == "deny_exempt" {
+      &mut self.deny_exempt
+    } else if ident == "capture" {
+      &mut self.capture
+    } else if ident == "span_events" {
+      &mut self.span_events
+    } else if ident == "span_events_env" {
+      &mut self.span_events_env
+    } else if ident == "color" {
+      &mut self.color
+    } else if ident == "format" {
+      &mut self.format
+    } else if ident == "log_files" {
+      &mut self.log_files
     } else {
       return Err(syn::Error::new_spanned(
         &name_value.path,
@@ -167,9 +921,74 @@ impl AttributeArgs {
       ))
     };
 
+    if arg_ref.is_some() {
+      return Err(syn::Error::new_spanned(
+        &name_value.path,
+        format!("Duplicate attribute `{ident}`."),
+      ))
+    }
+
     if let Expr::Lit(lit) = &name_value.value {
       if let Lit::Str(lit_str) = &lit.lit {
-        *arg_ref = Some(Cow::from(lit_str.value()));
+        if ident == "default_log_filter" || ident == "filter" {
+          *arg_ref = Some(Cow::from(filter::parse_filter(lit_str)?));
+        } else if ident == "deny_level" {
+          *arg_ref = Some(Cow::from(parse_level(lit_str)?));
+        } else if ident == "capture" {
+          let mode = lit_str.value();
+          if !CAPTURE_MODES.contains(&mode.as_str()) {
+            return Err(syn::Error::new_spanned(
+              lit_str,
+              format!(
+                "Invalid capture mode `{mode}`, expected one of {}.",
+                CAPTURE_MODES.join(", ")
+              ),
+            ))
+          }
+          *arg_ref = Some(Cow::from(mode));
+        } else if ident == "span_events" {
+          *arg_ref = Some(Cow::from(parse_span_events(lit_str)?));
+        } else if ident == "span_events_env" {
+          *arg_ref = Some(Cow::from(parse_env_var(lit_str)?));
+        } else if ident == "color" {
+          let color = lit_str.value();
+          if !COLORS.contains(&color.as_str()) {
+            return Err(syn::Error::new_spanned(
+              lit_str,
+              format!(
+                "Invalid color choice `{color}`, expected one of {}.",
+                COLORS.join(", ")
+              ),
+            ))
+          }
+          *arg_ref = Some(Cow::from(color));
+        } else if ident == "format" {
+          let format = lit_str.value();
+          if !FORMATS.contains(&format.as_str()) {
+            return Err(syn::Error::new_spanned(
+              lit_str,
+              format!(
+                "Invalid format `{format}`, expected one of {}.",
+                FORMATS.join(", ")
+              ),
+            ))
+          }
+          *arg_ref = Some(Cow::from(format));
+        } else if ident == "log_files" {
+          let mode = lit_str.value();
+          if !LOG_FILES_MODES.contains(&mode.as_str()) {
+            return Err(syn::Error::new_spanned(
+              lit_str,
+              format!(
+                "Invalid log files mode `{mode}`, expected one of {}.",
+                LOG_FILES_MODES.join(", ")
+              ),
+            ))
+          }
+          *arg_ref = Some(Cow::from(mode));
+        } else {
+          *arg_ref = Some(Cow::from(lit_str.value()));
+        }
       }
     }
 
@@ -182,100 +1001,171 @@ impl AttributeArgs {
       ))
     }
 
-    Ok(true)
+    Ok(())
+  }
+}
+
+
+/// Expand the path to the `test-log` crate.
+fn expand_crate(krate: Option<&Tokens>) -> Tokens {
+  match krate {
+    Some(krate) => krate.clone(),
+    None => quote! { ::test_log },
   }
 }
 
+/// Expand a string into an `Option` holding it.
+fn expand_some(value: &str) -> Tokens {
+  quote! { ::core::option::Option::Some(#value) }
+}
+
+/// Expand an expectation on the events emitted by the test.
+fn expand_expectation(expect: &ExpectLog, krate: &Tokens) -> Tokens {
+  let ExpectLog {
+    level,
+    target,
+    contains,
+  } = expect;
 
-/// Expand the initialization code for the `log` crate.
-#[cfg(all(feature = "log", not(feature = "trace")))]
-fn expand_logging_init(attribute_args: &AttributeArgs) -> Tokens {
-  let default_filter = attribute_args
-    .default_log_filter
-    .as_ref()
-    .unwrap_or(&Cow::Borrowed("info"));
+  let level = match level {
+    Some(level) => {
+      let mut chars = level.chars();
+      let variant = chars
+        .next()
+        .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+        .unwrap_or_default();
+      let variant = Ident::new(&variant, Span::call_site());
+      quote! { ::core::option::Option::Some(#krate::Level::#variant) }
+    },
+    None => quote! { ::core::option::Option::None },
+  };
+  let target = match target {
+    Some(target) => expand_some(target),
+    None => quote! { ::core::option::Option::None },
+  };
+  let contains = match contains {
+    Some(contains) => expand_some(contains),
+    None => quote! { ::core::option::Option::None },
+  };
 
   quote! {
-    {
-      let _result = ::test_log::env_logger::builder()
-        .parse_env(
-          ::test_log::env_logger::Env::default()
-            .default_filter_or(#default_filter)
-        )
-        .target(::test_log::env_logger::Target::Stderr)
-        .is_test(true)
-        .try_init();
+    #krate::__private::Expectation {
+      level: #level,
+      target: #target,
+      contains: #contains,
     }
   }
 }
 
-#[cfg(not(all(feature = "log", not(feature = "trace"))))]
-fn expand_logging_init(_attribute_args: &AttributeArgs) -> Tokens {
-  quote! {}
-}
+/// Expand the configuration of the test with name `ident`, to be passed
+/// to the runtime's initialization logic.
+///
+/// Besides the test's fully qualified name, only settings deviating
+/// from the default are emitted.
+/// Unless specified otherwise, crate-wide settings are taken from the
+/// `TEST_LOG_ENV`, `TEST_LOG_SPAN_EVENTS_ENV`, `TEST_LOG_DENY_LEVEL`,
+/// and `TEST_LOG_DENY_EXEMPT` environment variables at compile time.
+fn expand_config(attribute_args: &AttributeArgs, ident: &Ident) -> Tokens {
+  let AttributeArgs {
+    default_log_filter,
+    expect_log,
+    deny_level,
+    deny_exempt,
+    capture,
+    span_events,
+    env,
+    span_events_env,
+    color,
+    format,
+    log_files,
+    krate,
+  } = attribute_args;
+  let krate = expand_crate(krate.as_ref());
+  let (env_var, env_policy) = match env {
+    Some(Env { var, policy }) => (var.as_ref(), policy.as_ref()),
+    None => (None, None),
+  };
 
-/// Expand the initialization code for the `tracing` crate.
-#[cfg(feature = "trace")]
-fn expand_tracing_init(attribute_args: &AttributeArgs) -> Tokens {
-  let env_filter = if let Some(default_log_filter) = &attribute_args.default_log_filter {
-    quote! {
-      ::test_log::tracing_subscriber::EnvFilter::builder()
-        .with_default_directive(
-          #default_log_filter
-            .parse()
-            .expect("test-log: default_log_filter must be valid")
-        )
-        .from_env_lossy()
-    }
-  } else {
-    quote! {
-      ::test_log::tracing_subscriber::EnvFilter::builder()
-        .with_default_directive(
-          ::test_log::tracing_subscriber::filter::LevelFilter::INFO.into()
-        )
-        .from_env_lossy()
-    }
+  let mut fields = vec![quote! {
+    name: ::core::option::Option::Some(::core::concat!(
+      ::core::module_path!(),
+      "::",
+      ::core::stringify!(#ident)
+    ))
+  }];
+  if let Some(filter) = default_log_filter {
+    fields.push(quote! { filter: #filter });
+  }
+  if let Some(capture) = capture {
+    let capture = expand_some(capture);
+    fields.push(quote! { capture: #capture });
+  }
+  if let Some(color) = color {
+    let color = expand_some(color);
+    fields.push(quote! { color: #color });
+  }
+  if let Some(format) = format {
+    let format = expand_some(format);
+    fields.push(quote! { format: #format });
+  }
+  if let Some(log_files) = log_files {
+    let log_files = expand_some(log_files);
+    fields.push(quote! { log_files: #log_files });
+  }
+
+  let env_var = match env_var {
+    Some(var) => expand_some(var),
+    None => quote! { ::core::option_env!("TEST_LOG_ENV") },
   };
+  fields.push(quote! { env_var: #env_var });
+  if let Some(policy) = env_policy {
+    let policy = expand_some(policy);
+    fields.push(quote! { env_policy: #policy });
+  }
 
-  quote! {
-    {
-      let __internal_event_filter = {
-        use ::test_log::tracing_subscriber::fmt::format::FmtSpan;
-
-        match ::std::env::var_os("RUST_LOG_SPAN_EVENTS") {
-          Some(mut value) => {
-            value.make_ascii_lowercase();
-            let value = value.to_str().expect("test-log: RUST_LOG_SPAN_EVENTS must be valid UTF-8");
-            value
-              .split(",")
-              .map(|filter| match filter.trim() {
-                "new" => FmtSpan::NEW,
-                "enter" => FmtSpan::ENTER,
-                "exit" => FmtSpan::EXIT,
-                "close" => FmtSpan::CLOSE,
-                "active" => FmtSpan::ACTIVE,
-                "full" => FmtSpan::FULL,
-                _ => panic!("test-log: RUST_LOG_SPAN_EVENTS must contain filters separated by `,`.\n\t\
-                  For example: `active` or `new,close`\n\t\
-                  Supported filters: new, enter, exit, close, active, full\n\t\
-                  Got: {}", value),
-              })
-              .fold(FmtSpan::NONE, |acc, filter| filter | acc)
-          },
-          None => FmtSpan::NONE,
-        }
-      };
+  if let Some(span_events) = span_events {
+    let span_events = expand_some(span_events);
+    fields.push(quote! { span_events: #span_events });
+  }
+  let span_events_env = match span_events_env {
+    Some(var) => expand_some(var),
+    None => quote! { ::core::option_env!("TEST_LOG_SPAN_EVENTS_ENV") },
+  };
+  fields.push(quote! { span_events_env: #span_events_env });
 
-      let _ = ::test_log::tracing_subscriber::FmtSubscriber::builder()
-        .with_env_filter(#env_filter)
-        .with_span_events(__internal_event_filter)
-        .with_writer(::test_log::tracing_subscriber::fmt::TestWriter::with_stderr)
-        .try_init();
+  if !expect_log.is_empty() {
+    let expectations = expect_log
+      .iter()
+      .map(|expect| expand_expectation(expect, &krate));
+    fields.push(quote! { expect_log: &[#(#expectations),*] });
+  }
+
+  let deny_level = match deny_level {
+    Some(level) => expand_some(level),
+    None => quote! { ::core::option_env!("TEST_LOG_DENY_LEVEL") },
+  };
+  let deny_exempt = match deny_exempt {
+    Some(exempt) => expand_some(exempt),
+    None => quote! { ::core::option_env!("TEST_LOG_DENY_EXEMPT") },
+  };
+  fields.push(quote! { deny_level: #deny_level });
+  fields.push(quote! { deny_exempt: #deny_exempt });
+
+  quote! {
+    #krate::__private::Config {
+      #(#fields,)*
+      ..#krate::__private::Config::DEFAULT
     }
   }
 }
 
-#[cfg(not(feature = "trace"))]
-fn expand_tracing_init(_attribute_args: &AttributeArgs) -> Tokens {
-  quote! {}
+/// Expand a dependency on the manifest providing crate-wide defaults,
+/// so that the test gets rebuilt when they change.
+fn expand_manifest_dependency(manifest: &FsPath, used: bool) -> Tokens {
+  match manifest.to_str() {
+    Some(manifest) if used => quote! {
+      const _: &[u8] = ::core::include_bytes!(#manifest);
+    },
+    _ => quote! {},
+  }
 }
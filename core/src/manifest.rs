@@ -0,0 +1,283 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: (Apache-2.0 OR MIT)
+
+//! Retrieval of crate-wide defaults from the `[package.metadata.test-log]`
+//! table of the manifest of the crate under test.
+//!
+//! Each entry of the table is converted into the equivalent attribute
+//! argument, e.g., `filter = "debug"` or
+//! `env = { var = "MY_CRATE_LOG", policy = "merge" }`, and parsed as
+//! such. Errors are reported with the location of the offending value in
+//! the manifest.
+//!
+//! Furthermore, a dependency on `test-log` under a different name is
+//! detected, so that generated code refers to the crate by that name.
+//!
+//! As every test of a crate is expanded separately, the outcome of
+//! reading a manifest is cached for as long as it is not modified.
+
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::OnceLock;
+use std::sync::PoisonError;
+use std::time::SystemTime;
+
+use proc_macro2::Ident;
+use proc_macro2::Span;
+use proc_macro2::TokenStream as Tokens;
+
+use quote::quote;
+use quote::ToTokens as _;
+
+use syn::LitStr;
+use syn::Meta;
+
+use toml_edit::ImDocument;
+use toml_edit::Item;
+
+
+/// The keys supported in the `[package.metadata.test-log]` table.
+const KEYS: [&str; 12] = [
+  "crate",
+  "filter",
+  "default_log_filter",
+  "span_events",
+  "span_events_env",
+  "capture",
+  "color",
+  "format",
+  "log_files",
+  "env",
+  "deny_level",
+  "deny_exempt",
+];
+
+/// The name of the package providing the runtime.
+const PACKAGE: &str = "test-log";
+/// The tables that may contain dependencies on [`PACKAGE`].
+const DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "dev_dependencies"];
+
+
+/// Crate-wide defaults as read from a manifest.
+#[derive(Debug)]
+struct Defaults {
+  /// The attribute arguments corresponding to the entries of the
+  /// table, each along with the location of its value.
+  entries: Vec<(String, String)>,
+  /// Whether the manifest contributed any configuration.
+  used: bool,
+}
+
+
+/// Format the location of the value at `span` of the manifest at
+/// `path`.
+fn location(path: &Path, contents: &str, span: Option<Range<usize>>) -> String {
+  match span {
+    Some(span) => {
+      let before = &contents[..span.start];
+      let line = before.matches('\n').count() + 1;
+      let column = before
+        .rsplit('\n')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .count()
+        + 1;
+      format!("{}:{line}:{column}", path.display())
+    },
+    None => path.display().to_string(),
+  }
+}
+
+/// Create an error for the value at `location` of the manifest.
+fn error(location: &str, msg: &str) -> syn::Error {
+  syn::Error::new(
+    Span::call_site(),
+    format!("{location}: `[package.metadata.test-log]`: {msg}"),
+  )
+}
+
+/// Convert a key of the table into a token, as an identifier if
+/// possible and a string literal otherwise.
+fn key_tokens(key: &str) -> Tokens {
+  match syn::parse_str::<Ident>(key) {
+    Ok(ident) => quote! { #ident },
+    Err(_) => {
+      let lit = LitStr::new(key, Span::call_site());
+      quote! { #lit }
+    },
+  }
+}
+
+/// Convert an entry of the table into the equivalent attribute argument.
+fn entry_meta(key: &str, item: &Item) -> Result<Meta, String> {
+  let ident = Ident::new(key, Span::call_site());
+
+  if let Some(value) = item.as_str() {
+    let value = LitStr::new(value, Span::call_site());
+    return syn::parse2(quote! { #ident = #value }).map_err(|err| err.to_string())
+  }
+
+  if let Some(table) = item.as_table_like() {
+    let args = table
+      .iter()
+      .map(|(key, item)| {
+        let value = item
+          .as_str()
+          .ok_or_else(|| format!("expected a string value for `{key}`"))?;
+        let key = key_tokens(key);
+        let value = LitStr::new(value, Span::call_site());
+        Ok(quote! { #key = #value })
+      })
+      .collect::<Result<Vec<_>, String>>()?;
+    return syn::parse2(quote! { #ident(#(#args),*) }).map_err(|err| err.to_string())
+  }
+
+  Err(format!("expected a string or table value for `{key}`"))
+}
+
+/// Determine the name under which the crate depends on `test-log`, if
+/// it renamed the dependency and does not additionally depend on it
+/// under its original name.
+fn renamed_dependency(document: &ImDocument<&str>) -> Option<String> {
+  let targets = document
+    .get("target")
+    .and_then(Item::as_table_like)
+    .into_iter()
+    .flat_map(|targets| targets.iter().map(|(_, target)| target));
+  let tables = DEPENDENCY_TABLES
+    .iter()
+    .filter_map(|name| document.get(name))
+    .chain(targets.flat_map(|target| {
+      DEPENDENCY_TABLES
+        .iter()
+        .filter_map(move |name| target.get(name))
+    }))
+    .filter_map(Item::as_table_like);
+
+  let mut renamed = None;
+  for table in tables {
+    for (name, dep) in table.iter() {
+      let package = dep.get("package").and_then(Item::as_str).unwrap_or(name);
+      if package != PACKAGE {
+        continue
+      }
+      if name == PACKAGE {
+        return None
+      }
+      renamed = Some(name.replace('-', "_"));
+    }
+  }
+  renamed
+}
+
+
+/// Read the crate-wide defaults from the manifest at `path`.
+fn read_defaults(path: &Path) -> syn::Result<Defaults> {
+  let contents = fs::read_to_string(path).map_err(|err| {
+    syn::Error::new(
+      Span::call_site(),
+      format!("failed to read {}: {err}", path.display()),
+    )
+  })?;
+  let document = ImDocument::parse(contents.as_str())
+    .map_err(|err| error(&location(path, &contents, err.span()), err.message()))?;
+
+  let table = document
+    .get("package")
+    .and_then(|package| package.get("metadata"))
+    .and_then(|metadata| metadata.get("test-log"));
+  let mut entries = Vec::new();
+  let mut has_crate = false;
+
+  if let Some(table) = table {
+    let table = table
+      .as_table_like()
+      .ok_or_else(|| error(&location(path, &contents, table.span()), "expected a table"))?;
+
+    for (key, item) in table.iter() {
+      let location = location(path, &contents, item.span());
+      if !KEYS.contains(&key) {
+        let msg = format!(
+          "unrecognized key `{key}`, expected one of {}",
+          KEYS.join(", ")
+        );
+        return Err(error(&location, &msg))
+      }
+
+      let meta = entry_meta(key, item).map_err(|msg| error(&location, &msg))?;
+      entries.push((meta.to_token_stream().to_string(), location));
+      has_crate |= key == "crate";
+    }
+  }
+
+  let renamed = if has_crate {
+    None
+  } else {
+    renamed_dependency(&document)
+  };
+
+  if let Some(name) = &renamed {
+    let krate = LitStr::new(&format!("::{name}"), Span::call_site());
+    let meta = quote! { crate = #krate };
+    entries.push((meta.to_string(), location(path, &contents, None)));
+  }
+
+  Ok(Defaults {
+    entries,
+    used: table.is_some() || renamed.is_some(),
+  })
+}
+
+/// Retrieve the crate-wide defaults from the manifest at `path`,
+/// reading it only if it was not read before or got modified since.
+fn cached_defaults(path: &Path) -> syn::Result<Arc<Defaults>> {
+  type Cache = Mutex<HashMap<PathBuf, (SystemTime, Arc<Defaults>)>>;
+
+  static CACHE: OnceLock<Cache> = OnceLock::new();
+
+  let modified = fs::metadata(path)
+    .and_then(|metadata| metadata.modified())
+    .ok();
+  let cache = CACHE.get_or_init(Cache::default);
+
+  if let Some(modified) = modified {
+    let cache = cache.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some((time, defaults)) = cache.get(path) {
+      if *time == modified {
+        return Ok(Arc::clone(defaults))
+      }
+    }
+  }
+
+  let defaults = Arc::new(read_defaults(path)?);
+  if let Some(modified) = modified {
+    let mut cache = cache.lock().unwrap_or_else(PoisonError::into_inner);
+    let _prev = cache.insert(path.to_path_buf(), (modified, Arc::clone(&defaults)));
+  }
+  Ok(defaults)
+}
+
+
+/// Read the crate-wide defaults from the manifest at `path`, invoking
+/// `parse` with the attribute argument corresponding to each entry.
+///
+/// Returns whether the manifest contributed any configuration, i.e.,
+/// whether it contains a `[package.metadata.test-log]` table or a
+/// renamed dependency on `test-log`.
+pub(crate) fn parse_defaults<F>(path: &Path, mut parse: F) -> syn::Result<bool>
+where
+  F: FnMut(Meta) -> syn::Result<()>,
+{
+  let defaults = cached_defaults(path)?;
+  for (meta, location) in &defaults.entries {
+    let meta = syn::parse_str::<Meta>(meta)?;
+    let () = parse(meta).map_err(|err| error(location, &err.to_string()))?;
+  }
+  Ok(defaults.used)
+}
@@ -3,16 +3,28 @@
 
 //! Tests for error paths in `#[test_log(...)]` attribute parsing.
 
+use std::path::Path;
+
 use proc_macro2::TokenStream;
 
+use quote::quote;
+
 use syn::parse_quote;
 use syn::ItemFn;
+use syn::ItemMod;
 use syn::Meta;
 
 
 /// Try to expand a function that has the given `#[test_log(...)]`
 /// attribute, returning the error produced by `try_test`.
-fn expand_err(mut input: ItemFn) -> String {
+fn expand_err(input: ItemFn) -> String {
+  expand_err_with_manifest(input, None)
+}
+
+/// Try to expand a function like [`expand_err`], but with crate-wide
+/// defaults read from the manifest of the given name in
+/// `tests/manifests/`.
+fn expand_err_with_manifest(mut input: ItemFn, manifest: Option<&str>) -> String {
   let pos = input
     .attrs
     .iter()
@@ -28,9 +40,17 @@ fn expand_err(mut input: ItemFn) -> String {
     _ => TokenStream::new(),
   };
 
-  test_log_core::try_test(attr_args, input)
-    .unwrap_err()
-    .to_string()
+  let result = match manifest {
+    Some(manifest) => {
+      let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("manifests")
+        .join(manifest);
+      test_log_core::try_test_with_manifest(attr_args, input, Some(&path))
+    },
+    None => test_log_core::try_test(attr_args, input),
+  };
+  result.unwrap_err().to_string()
 }
 
 
@@ -90,3 +110,475 @@ fn reject_non_string_value() {
     "unexpected error: {err}",
   );
 }
+
+/// Check that `expect_log` rejects unknown levels.
+#[test]
+fn reject_expect_log_invalid_level() {
+  let err = expand_err(parse_quote! {
+    #[test_log::test]
+    #[test_log(expect_log(level = "verbose"))]
+    fn bad() {}
+  });
+  assert!(
+    err.contains("Invalid level `verbose`"),
+    "unexpected error: {err}"
+  );
+}
+
+/// Verify that `expect_log` rejects unknown arguments.
+#[test]
+fn reject_expect_log_unrecognized_arg() {
+  let err = expand_err(parse_quote! {
+    #[test_log::test]
+    #[test_log(expect_log(message = "x"))]
+    fn bad() {}
+  });
+  assert!(
+    err.contains("Unrecognized expect_log argument"),
+    "unexpected error: {err}",
+  );
+}
+
+/// Test that `expect_log` requires at least one criterion.
+#[test]
+fn reject_expect_log_empty() {
+  let err = expand_err(parse_quote! {
+    #[test_log::test]
+    #[test_log(expect_log())]
+    fn bad() {}
+  });
+  assert!(
+    err.contains("Expected at least one of"),
+    "unexpected error: {err}",
+  );
+}
+
+/// Check that `expect_log` rejects non-string values.
+#[test]
+fn reject_expect_log_non_string_value() {
+  let err = expand_err(parse_quote! {
+    #[test_log::test]
+    #[test_log(expect_log(contains = 42))]
+    fn bad() {}
+  });
+  assert!(
+    err.contains("expected string literal"),
+    "unexpected error: {err}"
+  );
+}
+
+/// Make sure that `deny_level` rejects unknown levels.
+#[test]
+fn reject_deny_level_invalid_level() {
+  let err = expand_err(parse_quote! {
+    #[test_log::test]
+    #[test_log(deny_level = "fatal")]
+    fn bad() {}
+  });
+  assert!(
+    err.contains("Invalid level `fatal`"),
+    "unexpected error: {err}"
+  );
+}
+
+/// Check that `capture` rejects unknown modes.
+#[test]
+fn reject_capture_invalid_mode() {
+  let err = expand_err(parse_quote! {
+    #[test_log::test]
+    #[test_log(capture = "always")]
+    fn bad() {}
+  });
+  assert!(
+    err.contains("Invalid capture mode `always`"),
+    "unexpected error: {err}"
+  );
+}
+
+/// Verify that an argument specified twice in the same attribute is
+/// rejected.
+#[test]
+fn reject_duplicate_arg() {
+  let err = expand_err(parse_quote! {
+    #[test_log::test]
+    #[test_log(default_log_filter = "debug", default_log_filter = "info")]
+    fn bad() {}
+  });
+  assert!(
+    err.contains("Duplicate attribute `default_log_filter`"),
+    "unexpected error: {err}",
+  );
+}
+
+/// Check that an argument specified in two separate attributes is
+/// rejected.
+#[test]
+fn reject_duplicate_arg_across_attrs() {
+  let err = expand_err(parse_quote! {
+    #[test_log::test]
+    #[test_log(deny_level = "warn")]
+    #[test_log(deny_exempt = "hyper", deny_level = "error")]
+    fn bad() {}
+  });
+  assert!(
+    err.contains("Duplicate attribute `deny_level`"),
+    "unexpected error: {err}",
+  );
+}
+
+/// Make sure that errors are reported for any argument of a list, not
+/// just the first one.
+#[test]
+fn reject_unrecognized_attr_in_list() {
+  let err = expand_err(parse_quote! {
+    #[test_log::test]
+    #[test_log(default_log_filter = "debug", bogus = "x")]
+    fn bad() {}
+  });
+  assert!(
+    err.contains("Unrecognized attribute"),
+    "unexpected error: {err}",
+  );
+}
+
+/// Check that the main attribute accepts only a single inner test
+/// attribute.
+#[test]
+fn reject_multiple_inner_test_attrs() {
+  let err = expand_err(parse_quote! {
+    #[test_log::test(tokio::test, rstest, filter = "debug")]
+    fn bad() {}
+  });
+  assert!(
+    err.contains("Expected at most one inner test attribute"),
+    "unexpected error: {err}",
+  );
+}
+
+/// Verify that `filter` and `default_log_filter` are treated as the same
+/// argument.
+#[test]
+fn reject_filter_and_default_log_filter() {
+  let err = expand_err(parse_quote! {
+    #[test_log::test(filter = "debug", default_log_filter = "info")]
+    fn bad() {}
+  });
+  assert!(
+    err.contains("Duplicate attribute `default_log_filter`"),
+    "unexpected error: {err}",
+  );
+}
+
+/// Make sure that unrecognized configuration in the main attribute is
+/// rejected.
+#[test]
+fn reject_unrecognized_inline_config() {
+  let err = expand_err(parse_quote! {
+    #[test_log::test(tokio::test, bogus = "x")]
+    fn bad() {}
+  });
+  assert!(
+    err.contains("Unrecognized attribute"),
+    "unexpected error: {err}",
+  );
+}
+
+/// Check that `span_events` rejects unknown events.
+#[test]
+fn reject_span_events_invalid_event() {
+  let err = expand_err(parse_quote! {
+    #[test_log::test(span_events = "new,destroy")]
+    fn bad() {}
+  });
+  assert!(
+    err.contains("Invalid span event `destroy`"),
+    "unexpected error: {err}",
+  );
+}
+
+/// Check that a filter directive with an unknown level is rejected, with
+/// the offending directive highlighted.
+#[test]
+fn reject_filter_invalid_level() {
+  let err = expand_err(parse_quote! {
+    #[test_log::test(filter = "info, hyper=verbose")]
+    fn bad() {}
+  });
+  assert!(
+    err.contains("Invalid filter directive `hyper=verbose`: unknown level `verbose`"),
+    "unexpected error: {err}",
+  );
+  assert!(
+    err.contains("  info, hyper=verbose\n        ^^^^^^^^^^^^^"),
+    "unexpected error: {err}",
+  );
+}
+
+/// Verify that a filter directive with an invalid target is rejected.
+#[test]
+fn reject_filter_invalid_target() {
+  let err = expand_err(parse_quote! {
+    #[test_log::test(filter = "my crate=debug")]
+    fn bad() {}
+  });
+  assert!(
+    err.contains("Invalid filter directive `my crate=debug`: invalid target `my crate`"),
+    "unexpected error: {err}",
+  );
+}
+
+/// Make sure that a filter directive with an unterminated span filter is
+/// rejected.
+#[test]
+fn reject_filter_unterminated_span() {
+  let err = expand_err(parse_quote! {
+    #[test_log::test(filter = "my_crate[request{id=1}=debug")]
+    fn bad() {}
+  });
+  assert!(
+    err.contains("unterminated span filter"),
+    "unexpected error: {err}",
+  );
+}
+
+/// Check that filters valid for either backend are accepted.
+#[test]
+fn accept_filter_directives() {
+  let filters = [
+    "debug",
+    "DEBUG",
+    "3",
+    "my_crate",
+    "my_crate::module=trace,hyper=off",
+    "my_crate[request{id=1,method}]=debug",
+    "[request]=info",
+    "my_crate=",
+    "info,my_crate=debug/retry.*",
+  ];
+
+  for filter in filters {
+    let result =
+      test_log_core::try_test(quote! { filter = #filter }, parse_quote! { fn good() {} });
+    assert!(result.is_ok(), "`{filter}` rejected: {:?}", result.err());
+  }
+}
+
+/// Check that the structured `filter(...)` form rejects unknown levels.
+#[test]
+fn reject_structured_filter_invalid_level() {
+  let err = expand_err(parse_quote! {
+    #[test_log::test(filter(default = "info", hyper = "verbose"))]
+    fn bad() {}
+  });
+  assert!(
+    err.contains("Invalid level `verbose`"),
+    "unexpected error: {err}",
+  );
+}
+
+/// Check that the structured `filter(...)` form only accepts levels by
+/// name.
+#[test]
+fn reject_structured_filter_numeric_level() {
+  let err = expand_err(parse_quote! {
+    #[test_log::test(filter(default = "info", hyper = "3"))]
+    fn bad() {}
+  });
+  assert!(err.contains("Invalid level `3`"), "unexpected error: {err}");
+}
+
+/// Verify that the structured `filter(...)` form rejects targets
+/// specified more than once.
+#[test]
+fn reject_structured_filter_duplicate_target() {
+  let err = expand_err(parse_quote! {
+    #[test_log::test(filter(hyper = "info", "hyper" = "warn"))]
+    fn bad() {}
+  });
+  assert!(
+    err.contains("Duplicate filter target `hyper`"),
+    "unexpected error: {err}",
+  );
+}
+
+/// Make sure that the structured `filter(...)` form cannot be combined
+/// with a filter string.
+#[test]
+fn reject_structured_filter_and_filter_string() {
+  let err = expand_err(parse_quote! {
+    #[test_log::test(filter = "debug", filter(default = "info"))]
+    fn bad() {}
+  });
+  assert!(
+    err.contains("Duplicate attribute `filter`"),
+    "unexpected error: {err}",
+  );
+}
+
+/// Check that `env` rejects unknown policies.
+#[test]
+fn reject_env_invalid_policy() {
+  let err = expand_err(parse_quote! {
+    #[test_log::test(env(policy = "replace"))]
+    fn bad() {}
+  });
+  assert!(
+    err.contains("Invalid environment policy `replace`"),
+    "unexpected error: {err}",
+  );
+}
+
+/// Check that `env` rejects invalid environment variable names.
+#[test]
+fn reject_env_invalid_var() {
+  let err = expand_err(parse_quote! {
+    #[test_log::test(env = "MY-CRATE-LOG")]
+    fn bad() {}
+  });
+  assert!(
+    err.contains("Invalid environment variable name `MY-CRATE-LOG`"),
+    "unexpected error: {err}",
+  );
+
+  let err = expand_err(parse_quote! {
+    #[test_log::test(span_events_env = "1SPAN")]
+    fn bad() {}
+  });
+  assert!(
+    err.contains("Invalid environment variable name `1SPAN`"),
+    "unexpected error: {err}",
+  );
+}
+
+/// Make sure that the variable name and policy cannot be specified via
+/// separate `env` attributes.
+#[test]
+fn reject_duplicate_env() {
+  let err = expand_err(parse_quote! {
+    #[test_log::test(env = "MY_CRATE_LOG", env = "merge")]
+    fn bad() {}
+  });
+  assert!(
+    err.contains("Duplicate attribute `env`"),
+    "unexpected error: {err}",
+  );
+}
+
+/// Check that `color` rejects unknown choices.
+#[test]
+fn reject_color_invalid_choice() {
+  let err = expand_err(parse_quote! {
+    #[test_log::test(color = "sometimes")]
+    fn bad() {}
+  });
+  assert!(
+    err.contains("Invalid color choice `sometimes`"),
+    "unexpected error: {err}",
+  );
+}
+
+/// Check that `format` rejects unknown formats.
+#[test]
+fn reject_format_invalid_format() {
+  let err = expand_err(parse_quote! {
+    #[test_log::test(format = "yaml")]
+    fn bad() {}
+  });
+  assert!(
+    err.contains("Invalid format `yaml`"),
+    "unexpected error: {err}",
+  );
+}
+
+/// Check that `log_files` rejects unknown modes.
+#[test]
+fn reject_log_files_invalid_mode() {
+  let err = expand_err(parse_quote! {
+    #[test_log::test(log_files = "always")]
+    fn bad() {}
+  });
+  assert!(
+    err.contains("Invalid log files mode `always`"),
+    "unexpected error: {err}",
+  );
+}
+
+/// Check that `crate` rejects values that are not paths.
+#[test]
+fn reject_crate_invalid_path() {
+  let err = expand_err(parse_quote! {
+    #[test_log::test(crate = "not a path")]
+    fn bad() {}
+  });
+  assert!(
+    err.contains("Invalid crate path `not a path`"),
+    "unexpected error: {err}",
+  );
+}
+
+/// Make sure that invalid values in the manifest are reported along
+/// with their location.
+#[test]
+fn reject_manifest_invalid_filter() {
+  let err = expand_err_with_manifest(
+    parse_quote! {
+      #[test_log::test]
+      fn bad() {}
+    },
+    Some("invalid_filter.toml"),
+  );
+  assert!(
+    err.contains("invalid_filter.toml:7:10: `[package.metadata.test-log]`"),
+    "unexpected error: {err}",
+  );
+  assert!(
+    err.contains("Invalid filter directive `my_crate=verbose`"),
+    "unexpected error: {err}",
+  );
+}
+
+/// Make sure that keys in the manifest not applicable crate-wide are
+/// rejected.
+#[test]
+fn reject_manifest_unknown_key() {
+  let err = expand_err_with_manifest(
+    parse_quote! {
+      #[test_log::test]
+      fn bad() {}
+    },
+    Some("unknown_key.toml"),
+  );
+  assert!(
+    err.contains(
+      "unknown_key.toml:6:14: `[package.metadata.test-log]`: unrecognized key `expect_log`"
+    ),
+    "unexpected error: {err}",
+  );
+}
+
+/// Make sure that `test_module` can only be applied to inline modules.
+#[test]
+fn reject_test_module_non_inline() {
+  let input: ItemMod = parse_quote! { mod tests; };
+  let err = test_log_core::try_test_module(TokenStream::new(), input)
+    .unwrap_err()
+    .to_string();
+  assert!(
+    err.contains("Expected an inline module"),
+    "unexpected error: {err}",
+  );
+}
+
+/// Make sure that `test_module` does not accept an inner test
+/// attribute.
+#[test]
+fn reject_test_module_inner_test_attr() {
+  let input: ItemMod = parse_quote! { mod tests {} };
+  let err = test_log_core::try_test_module(quote! { tokio::test }, input)
+    .unwrap_err()
+    .to_string();
+  assert!(
+    err.contains("Expected only configuration"),
+    "unexpected error: {err}",
+  );
+}
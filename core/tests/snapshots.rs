@@ -3,11 +3,14 @@
 
 //! Snapshot tests for the macro expansion logic.
 
+use std::path::Path;
+
 use proc_macro2::TokenStream;
 
 use syn::parse2;
 use syn::parse_quote;
 use syn::ItemFn;
+use syn::ItemMod;
 use syn::Meta;
 
 
@@ -24,7 +27,14 @@ const BACKEND: &str = "log_trace";
 /// Accept a fully-defined test function annotated with
 /// `#[test_log::test(...)]`, extract the attribute arguments, strip
 /// that attribute from the function, and expand via `try_test`.
-fn expand(mut input: ItemFn) -> String {
+fn expand(input: ItemFn) -> String {
+  expand_with_manifest(input, None)
+}
+
+/// Expand the provided test function like [`expand`], but with
+/// crate-wide defaults read from the manifest of the given name in
+/// `tests/manifests/`.
+fn expand_with_manifest(mut input: ItemFn, manifest: Option<&str>) -> String {
   let pos = input
     .attrs
     .iter()
@@ -40,7 +50,33 @@ fn expand(mut input: ItemFn) -> String {
     _ => TokenStream::new(),
   };
 
-  let tokens = test_log_core::try_test(attr_args, input).unwrap();
+  let tokens = match manifest {
+    Some(manifest) => {
+      let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("manifests")
+        .join(manifest);
+      test_log_core::try_test_with_manifest(attr_args, input, Some(&path)).unwrap()
+    },
+    None => test_log_core::try_test(attr_args, input).unwrap(),
+  };
+  format(tokens)
+}
+
+/// Accept a module annotated with `#[test_log::test_module(...)]` and
+/// expand it via `try_test_module`.
+fn expand_module(mut input: ItemMod) -> String {
+  let attr = input.attrs.remove(0);
+  let attr_args = match &attr.meta {
+    Meta::List(list) => list.tokens.clone(),
+    _ => TokenStream::new(),
+  };
+
+  format(test_log_core::try_test_module(attr_args, input).unwrap())
+}
+
+/// Format the expanded `tokens` for inclusion in a snapshot.
+fn format(tokens: TokenStream) -> String {
   let file = parse2(tokens).unwrap();
 
   // Format in a nice way, but halve indentation to match the project's
@@ -54,6 +90,8 @@ fn expand(mut input: ItemFn) -> String {
     })
     .collect::<Vec<_>>()
     .join("\n")
+    // Keep snapshots independent of the location of the checkout.
+    .replace(env!("CARGO_MANIFEST_DIR"), "$CARGO_MANIFEST_DIR")
 }
 
 macro_rules! assert_snapshot {
@@ -136,3 +174,236 @@ fn default_log_filter() {
   });
   assert_snapshot!(output);
 }
+
+/// Check that `expect_log` expectations are registered with the scope.
+#[cfg(feature = "unstable")]
+#[test]
+fn expect_log() {
+  let output = expand(parse_quote! {
+    #[test_log::test]
+    #[test_log(expect_log(level = "WARN", contains = "retrying"))]
+    #[test_log(expect_log(target = "my_crate::db"))]
+    fn with_expectations() {}
+  });
+  assert_snapshot!(output);
+}
+
+/// Verify that `deny_level` and `deny_exempt` take precedence over the
+/// crate-wide configuration.
+#[cfg(feature = "unstable")]
+#[test]
+fn deny_level() {
+  let output = expand(parse_quote! {
+    #[test_log::test]
+    #[test_log(deny_level = "Error")]
+    #[test_log(deny_exempt = "hyper,h2")]
+    fn with_deny_level() {}
+  });
+  assert_snapshot!(output);
+}
+
+/// Check that `capture` is passed on to the scope.
+#[cfg(feature = "unstable")]
+#[test]
+fn capture() {
+  let output = expand(parse_quote! {
+    #[test_log::test]
+    #[test_log(capture = "on-failure")]
+    fn with_capture() {}
+  });
+  assert_snapshot!(output);
+}
+
+/// Verify that multiple arguments can be provided in a single
+/// `#[test_log(...)]` attribute.
+#[cfg(feature = "unstable")]
+#[test]
+fn multiple_args() {
+  let output = expand(parse_quote! {
+    #[test_log::test]
+    #[test_log(
+      default_log_filter = "debug",
+      deny_level = "error",
+      expect_log(level = "info", contains = "started"),
+    )]
+    fn with_multiple_args() {}
+  });
+  assert_snapshot!(output);
+}
+
+/// Check that configuration can be provided in the main attribute,
+/// along with an inner test attribute.
+#[test]
+fn inline_config() {
+  let output = expand(parse_quote! {
+    #[test_log::test(tokio::test, filter = "debug", deny_level = "error")]
+    async fn with_inline_config() {}
+  });
+  assert_snapshot!(output);
+}
+
+/// Verify that configuration in the main attribute does not require an
+/// inner test attribute.
+#[test]
+fn inline_config_without_inner_test() {
+  let output = expand(parse_quote! {
+    #[test_log::test(filter = "debug")]
+    fn with_inline_config() {}
+  });
+  assert_snapshot!(output);
+}
+
+/// Check that `span_events` is normalized and passed on.
+#[test]
+fn span_events() {
+  let output = expand(parse_quote! {
+    #[test_log::test(span_events = "New, CLOSE")]
+    fn with_span_events() {}
+  });
+  assert_snapshot!(output);
+}
+
+/// Verify that the structured `filter(...)` form expands to the
+/// equivalent directives.
+#[test]
+fn structured_filter() {
+  let output = expand(parse_quote! {
+    #[test_log::test(filter(my_crate = "trace", default = "info", hyper = "WARN", "my_crate::db" = "debug"))]
+    fn with_structured_filter() {}
+  });
+  assert_snapshot!(output);
+}
+
+/// Check that the `env` policy is passed on to the scope.
+#[test]
+fn env_policy() {
+  let output = expand(parse_quote! {
+    #[test_log::test(filter = "info", env = "merge")]
+    fn with_env_policy() {}
+  });
+  assert_snapshot!(output);
+}
+
+/// Check that values of `env` other than policies are taken for
+/// variable names, regardless of their case.
+#[test]
+fn env_var_lower_case() {
+  let output = expand(parse_quote! {
+    #[test_log::test(env = "my_crate_log")]
+    fn with_env_var() {}
+  });
+  assert_snapshot!(output);
+}
+
+/// Check that custom environment variable names are passed on to the
+/// scope and the span event configuration.
+#[test]
+fn env_var() {
+  let output = expand(parse_quote! {
+    #[test_log::test(
+      env(var = "MY_CRATE_LOG", policy = "merge"),
+      span_events_env = "MY_CRATE_SPAN_EVENTS",
+    )]
+    fn with_env_var() {}
+  });
+  assert_snapshot!(output);
+}
+
+/// Check that crate-wide defaults are read from the manifest.
+#[test]
+fn manifest_defaults() {
+  let output = expand_with_manifest(
+    parse_quote! {
+      #[test_log::test]
+      fn with_manifest_defaults() {}
+    },
+    Some("defaults.toml"),
+  );
+  assert_snapshot!(output);
+}
+
+/// Check that per-test attributes take precedence over crate-wide
+/// defaults from the manifest.
+#[test]
+fn manifest_defaults_overridden() {
+  let output = expand_with_manifest(
+    parse_quote! {
+      #[test_log::test(filter = "trace", color = "always", env = "ignore")]
+      fn with_overridden_manifest_defaults() {}
+    },
+    Some("defaults.toml"),
+  );
+  assert_snapshot!(output);
+}
+
+/// Check that a test's own `deny_exempt` is kept when `deny_level` is
+/// taken from the crate-wide defaults.
+#[test]
+fn manifest_defaults_own_deny_exempt() {
+  let output = expand_with_manifest(
+    parse_quote! {
+      #[test_log::test(deny_exempt = "mine")]
+      fn with_own_deny_exempt() {}
+    },
+    Some("defaults.toml"),
+  );
+  assert_snapshot!(output);
+}
+
+/// Check that generated code refers to the crate via the path provided
+/// through `crate`.
+#[test]
+fn crate_path() {
+  let output = expand(parse_quote! {
+    #[test_log::test(crate = "my_crate::test_log", expect_log(contains = "done"))]
+    async fn with_crate_path() {}
+  });
+  assert_snapshot!(output);
+}
+
+/// Check that a renamed dependency on `test-log` is detected from the
+/// manifest.
+#[test]
+fn manifest_renamed_dependency() {
+  let output = expand_with_manifest(
+    parse_quote! {
+      #[test_log::test]
+      fn with_renamed_dependency() {}
+    },
+    Some("renamed.toml"),
+  );
+  assert_snapshot!(output);
+}
+
+/// Check that `test_module` expands the tests of a module, passing on the
+/// module's configuration.
+#[test]
+fn test_module() {
+  let output = expand_module(parse_quote! {
+    #[test_log::test_module(filter = "debug")]
+    mod tests {
+      #[tokio::test]
+      async fn async_test() {}
+
+      fn helper() {}
+    }
+  });
+  assert_snapshot!(output);
+}
+
+/// Check that `test_module` recognizes our attribute when referred to
+/// via an absolute path or the path to the crate.
+#[test]
+fn test_module_crate_path() {
+  let output = expand_module(parse_quote! {
+    #[test_log::test_module(crate = "my_crate::test_log")]
+    mod tests {
+      #[::test_log::test(filter = "debug")]
+      fn with_absolute_path() {}
+
+      #[my_crate::test_log::test(filter = "trace")]
+      fn with_crate_path() {}
+    }
+  });
+  assert_snapshot!(output);
+}
@@ -8,8 +8,10 @@ use proc_macro::TokenStream;
 use syn::parse_macro_input;
 use syn::Error;
 use syn::ItemFn;
+use syn::ItemMod;
 
 use test_log_core::try_test;
+use test_log_core::try_test_module;
 
 
 // Documented in `test-log` crate's re-export.
@@ -21,3 +23,13 @@ pub fn test(attr: TokenStream, item: TokenStream) -> TokenStream {
     .unwrap_or_else(Error::into_compile_error)
     .into()
 }
+
+// Documented in `test-log` crate's re-export.
+#[allow(missing_docs)]
+#[proc_macro_attribute]
+pub fn test_module(attr: TokenStream, item: TokenStream) -> TokenStream {
+  let item = parse_macro_input!(item as ItemMod);
+  try_test_module(attr.into(), item)
+    .unwrap_or_else(Error::into_compile_error)
+    .into()
+}
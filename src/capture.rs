@@ -0,0 +1,529 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: (Apache-2.0 OR MIT)
+
+//! In-memory capture of log records and tracing events emitted by a
+//! test.
+
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+
+use crate::scope;
+
+
+/// The severity of a captured [`Event`].
+///
+/// Levels are ordered by verbosity, just like they are in the `log` and
+/// `tracing` crates: [`Level::Error`] is the "smallest" and
+/// [`Level::Trace`] the "largest" level.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Level {
+  /// The "error" level.
+  Error = 1,
+  /// The "warn" level.
+  Warn,
+  /// The "info" level.
+  Info,
+  /// The "debug" level.
+  Debug,
+  /// The "trace" level.
+  Trace,
+}
+
+impl Level {
+  /// Retrieve the lower case name of the level.
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Self::Error => "error",
+      Self::Warn => "warn",
+      Self::Info => "info",
+      Self::Debug => "debug",
+      Self::Trace => "trace",
+    }
+  }
+}
+
+impl Display for Level {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    f.pad(self.as_str())
+  }
+}
+
+impl FromStr for Level {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let level = match s.to_ascii_lowercase().as_str() {
+      "error" => Self::Error,
+      "warn" => Self::Warn,
+      "info" => Self::Info,
+      "debug" => Self::Debug,
+      "trace" => Self::Trace,
+      _ => return Err(format!("invalid level `{s}`")),
+    };
+    Ok(level)
+  }
+}
+
+#[cfg(feature = "log")]
+impl From<logging::Level> for Level {
+  fn from(level: logging::Level) -> Self {
+    match level {
+      logging::Level::Error => Self::Error,
+      logging::Level::Warn => Self::Warn,
+      logging::Level::Info => Self::Info,
+      logging::Level::Debug => Self::Debug,
+      logging::Level::Trace => Self::Trace,
+    }
+  }
+}
+
+#[cfg(feature = "log")]
+impl From<Level> for logging::Level {
+  fn from(level: Level) -> Self {
+    match level {
+      Level::Error => Self::Error,
+      Level::Warn => Self::Warn,
+      Level::Info => Self::Info,
+      Level::Debug => Self::Debug,
+      Level::Trace => Self::Trace,
+    }
+  }
+}
+
+#[cfg(feature = "trace")]
+impl From<tracing::Level> for Level {
+  fn from(level: tracing::Level) -> Self {
+    match level {
+      tracing::Level::ERROR => Self::Error,
+      tracing::Level::WARN => Self::Warn,
+      tracing::Level::INFO => Self::Info,
+      tracing::Level::DEBUG => Self::Debug,
+      tracing::Level::TRACE => Self::Trace,
+    }
+  }
+}
+
+#[cfg(feature = "trace")]
+impl From<Level> for tracing::Level {
+  fn from(level: Level) -> Self {
+    match level {
+      Level::Error => Self::ERROR,
+      Level::Warn => Self::WARN,
+      Level::Info => Self::INFO,
+      Level::Debug => Self::DEBUG,
+      Level::Trace => Self::TRACE,
+    }
+  }
+}
+
+
+/// A `log` record or `tracing` event captured while running a test.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Event {
+  pub(crate) level: Level,
+  pub(crate) target: String,
+  pub(crate) message: String,
+  pub(crate) module_path: Option<String>,
+  pub(crate) file: Option<String>,
+  pub(crate) line: Option<u32>,
+  pub(crate) fields: Vec<(String, String)>,
+}
+
+impl Event {
+  /// The level the event was emitted at.
+  pub fn level(&self) -> Level {
+    self.level
+  }
+
+  /// The event's target, typically the path of the module emitting it.
+  pub fn target(&self) -> &str {
+    &self.target
+  }
+
+  /// The event's formatted message.
+  pub fn message(&self) -> &str {
+    &self.message
+  }
+
+  /// The path of the module the event was emitted from, if known.
+  pub fn module_path(&self) -> Option<&str> {
+    self.module_path.as_deref()
+  }
+
+  /// The source file the event was emitted from, if known.
+  pub fn file(&self) -> Option<&str> {
+    self.file.as_deref()
+  }
+
+  /// The line in the source file the event was emitted from, if known.
+  pub fn line(&self) -> Option<u32> {
+    self.line
+  }
+
+  /// The event's structured fields (excluding the message), as name and
+  /// formatted value pairs.
+  ///
+  /// For `log` records these are the record's key-value pairs.
+  pub fn fields(&self) -> impl Iterator<Item = (&str, &str)> {
+    self
+      .fields
+      .iter()
+      .map(|(name, value)| (name.as_str(), value.as_str()))
+  }
+
+  /// Look up the formatted value of the structured field with the given
+  /// name.
+  pub fn field(&self, name: &str) -> Option<&str> {
+    self
+      .fields()
+      .find_map(|(field, value)| (field == name).then_some(value))
+  }
+}
+
+impl Display for Event {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    write!(f, "{:>5} {}: {}", self.level, self.target, self.message)?;
+    for (name, value) in self.fields() {
+      write!(f, " {name}={value}")?;
+    }
+    Ok(())
+  }
+}
+
+
+/// A query selecting a subset of captured [`Event`]s.
+///
+/// An empty query matches all events; each criterion narrows down the
+/// set of matched events further.
+///
+/// ```rust
+/// use test_log::Level;
+/// use test_log::Query;
+///
+/// let query = Query::new()
+///   .level(Level::Warn)
+///   .contains("retrying")
+///   .field("attempt", "2");
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Query {
+  pub(crate) level: Option<Level>,
+  pub(crate) target: Option<String>,
+  pub(crate) contains: Option<String>,
+  pub(crate) fields: Vec<(String, String)>,
+}
+
+impl Query {
+  /// Create a new query matching all events.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Only match events emitted at exactly the given level.
+  pub fn level(mut self, level: Level) -> Self {
+    self.level = Some(level);
+    self
+  }
+
+  /// Only match events whose target is `target` or a module nested in
+  /// it (e.g., `my_crate` matches `my_crate::db` as well).
+  pub fn target(mut self, target: impl Into<String>) -> Self {
+    self.target = Some(target.into());
+    self
+  }
+
+  /// Only match events whose message contains `needle`.
+  pub fn contains(mut self, needle: impl Into<String>) -> Self {
+    self.contains = Some(needle.into());
+    self
+  }
+
+  /// Only match events having a structured field `name` that is
+  /// formatted as `value`.
+  pub fn field(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+    self.fields.push((name.into(), value.into()));
+    self
+  }
+
+  /// Check whether the given event matches the query.
+  pub fn matches(&self, event: &Event) -> bool {
+    if let Some(level) = self.level {
+      if event.level != level {
+        return false
+      }
+    }
+
+    if let Some(target) = &self.target {
+      let nested = event
+        .target
+        .strip_prefix(target.as_str())
+        .map(|rest| rest.is_empty() || rest.starts_with("::"))
+        .unwrap_or(false);
+      if !nested {
+        return false
+      }
+    }
+
+    if let Some(needle) = &self.contains {
+      if !event.message.contains(needle.as_str()) {
+        return false
+      }
+    }
+
+    self
+      .fields
+      .iter()
+      .all(|(name, value)| event.field(name) == Some(value.as_str()))
+  }
+}
+
+impl Display for Query {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    let mut criteria = Vec::new();
+    if let Some(level) = self.level {
+      criteria.push(format!("level = {level}"));
+    }
+    if let Some(target) = &self.target {
+      criteria.push(format!("target = {target:?}"));
+    }
+    if let Some(contains) = &self.contains {
+      criteria.push(format!("contains = {contains:?}"));
+    }
+    for (name, value) in &self.fields {
+      criteria.push(format!("{name} = {value:?}"));
+    }
+
+    if criteria.is_empty() {
+      f.write_str("any event")
+    } else {
+      f.write_str(&criteria.join(", "))
+    }
+  }
+}
+
+
+/// A handle to the events captured while running a test.
+///
+/// Every `log` record and `tracing` event that is emitted on the test's
+/// thread and that passes the test's log filter is recorded. Events not
+/// passing the filter are recorded only as far as needed for checking
+/// the test's `expect_log` expectations and `deny_level`. A handle for
+/// the current test can be retrieved using [`captured`].
+#[derive(Clone, Debug, Default)]
+pub struct Captured {
+  events: Arc<Mutex<Vec<Event>>>,
+}
+
+impl Captured {
+  #[cfg(any(feature = "log", feature = "trace"))]
+  pub(crate) fn push(&self, event: Event) {
+    self
+      .events
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .push(event);
+  }
+
+  /// Retrieve all events captured so far.
+  pub fn events(&self) -> Vec<Event> {
+    self
+      .events
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .clone()
+  }
+
+  /// Retrieve all captured events matching the given query.
+  pub fn find(&self, query: &Query) -> Vec<Event> {
+    self
+      .events
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .iter()
+      .filter(|event| query.matches(event))
+      .cloned()
+      .collect()
+  }
+
+  /// Check whether any captured event matches the given query.
+  pub fn contains(&self, query: &Query) -> bool {
+    self
+      .events
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .iter()
+      .any(|event| query.matches(event))
+  }
+
+  /// Discard all events captured so far.
+  pub fn clear(&self) {
+    self
+      .events
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .clear();
+  }
+}
+
+
+/// Retrieve a handle to the events captured by the currently running
+/// test.
+///
+/// ```rust
+/// use test_log::Level;
+/// use test_log::Query;
+///
+/// # use logging as log;
+/// # // Note that no test would actually run, regardless of `no_run`,
+/// # // because we do not invoke the function.
+/// #[test_log::test]
+/// fn retries() {
+///   log::warn!("request failed; retrying");
+///
+///   let captured = test_log::captured();
+///   assert!(captured.contains(&Query::new().level(Level::Warn).contains("retrying")));
+/// }
+/// ```
+///
+/// # Panics
+/// This function panics if called from outside a test annotated with
+/// the crate's `#[test]` attribute.
+pub fn captured() -> Captured {
+  scope::current()
+    .map(|shared| shared.captured.clone())
+    .expect("test_log::captured() must be called from within a #[test_log::test]")
+}
+
+
+#[cfg(all(feature = "log", not(feature = "trace")))]
+mod log {
+  use logging::kv::Error as KvError;
+  use logging::kv::Key;
+  use logging::kv::Value;
+  use logging::kv::VisitSource;
+  use logging::Record;
+
+  use super::Event;
+
+
+  /// A visitor collecting a record's key-value pairs.
+  struct Visitor(Vec<(String, String)>);
+
+  impl<'kvs> VisitSource<'kvs> for Visitor {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), KvError> {
+      self.0.push((key.to_string(), value.to_string()));
+      Ok(())
+    }
+  }
+
+  impl From<&Record<'_>> for Event {
+    fn from(record: &Record<'_>) -> Self {
+      let mut visitor = Visitor(Vec::new());
+      let _result = record.key_values().visit(&mut visitor);
+
+      Self {
+        level: record.level().into(),
+        target: record.target().to_string(),
+        message: record.args().to_string(),
+        module_path: record.module_path().map(str::to_string),
+        file: record.file().map(str::to_string),
+        line: record.line(),
+        fields: visitor.0,
+      }
+    }
+  }
+}
+
+
+#[cfg(feature = "trace")]
+mod trace {
+  use std::fmt::Debug;
+
+  use tracing::field::Field;
+  use tracing::field::Visit;
+  use tracing::Subscriber;
+
+  use tracing_subscriber::layer::Context;
+  use tracing_subscriber::Layer;
+
+  use crate::scope;
+
+  use super::Event;
+
+
+  /// A visitor collecting an event's message and fields.
+  ///
+  /// Events originating from the `log` crate carry their metadata in
+  /// dedicated `log.*` fields, which are mapped back onto the event.
+  struct Visitor<'e>(&'e mut Event);
+
+  impl Visit for Visitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+      match field.name() {
+        "message" => self.0.message = value.to_string(),
+        "log.target" => self.0.target = value.to_string(),
+        "log.module_path" => self.0.module_path = Some(value.to_string()),
+        "log.file" => self.0.file = Some(value.to_string()),
+        name => self.0.fields.push((name.to_string(), value.to_string())),
+      }
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+      match field.name() {
+        "log.line" => self.0.line = u32::try_from(value).ok(),
+        _ => self.record_debug(field, &value),
+      }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+      match field.name() {
+        "message" => self.0.message = format!("{value:?}"),
+        name if name.starts_with("log.") => (),
+        name => self.0.fields.push((name.to_string(), format!("{value:?}"))),
+      }
+    }
+  }
+
+
+  impl From<&tracing::Event<'_>> for Event {
+    fn from(event: &tracing::Event<'_>) -> Self {
+      let metadata = event.metadata();
+      let mut captured = Self {
+        level: (*metadata.level()).into(),
+        target: metadata.target().to_string(),
+        message: String::new(),
+        module_path: metadata.module_path().map(str::to_string),
+        file: metadata.file().map(str::to_string),
+        line: metadata.line(),
+        fields: Vec::new(),
+      };
+      event.record(&mut Visitor(&mut captured));
+      captured
+    }
+  }
+
+
+  /// A layer recording all events it sees in the capture buffer of the
+  /// test whose scope is entered on the current thread.
+  #[derive(Debug)]
+  pub(crate) struct CaptureLayer;
+
+  impl<S> Layer<S> for CaptureLayer
+  where
+    S: Subscriber,
+  {
+    fn on_event(&self, event: &tracing::Event<'_>, _ctx: Context<'_, S>) {
+      if let Some(shared) = scope::current() {
+        shared.captured.push(event.into());
+      }
+    }
+  }
+}
+
+#[cfg(feature = "trace")]
+pub(crate) use trace::CaptureLayer;
@@ -0,0 +1,152 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: (Apache-2.0 OR MIT)
+
+//! Checks on the events captured by a test, performed once the test
+//! body has finished.
+
+use std::cmp::Reverse;
+use std::fmt::Write as _;
+
+use crate::capture::Captured;
+use crate::capture::Event;
+use crate::capture::Level;
+use crate::capture::Query;
+
+
+/// The maximum number of captured events to list when an expectation
+/// is not met.
+const MAX_NEAREST: usize = 5;
+
+
+/// Compute the length of the longest common substring of `a` and `b`,
+/// in characters.
+fn longest_common_substring(a: &str, b: &str) -> usize {
+  let b = b.chars().collect::<Vec<_>>();
+  let mut previous = vec![0; b.len() + 1];
+  let mut longest = 0;
+
+  for c in a.chars() {
+    let mut current = vec![0; b.len() + 1];
+    for (j, d) in b.iter().enumerate() {
+      if c == *d {
+        current[j + 1] = previous[j] + 1;
+        longest = longest.max(current[j + 1]);
+      }
+    }
+    previous = current;
+  }
+  longest
+}
+
+/// Score how close `event` comes to matching `query`; the higher the
+/// score, the closer the match.
+fn closeness(query: &Query, event: &Event) -> (usize, usize) {
+  let mut criteria = 0;
+  if query.level.is_some_and(|level| level == event.level()) {
+    criteria += 1;
+  }
+  if let Some(target) = &query.target {
+    if Query::new().target(target).matches(event) {
+      criteria += 1;
+    }
+  }
+  let similarity = query
+    .contains
+    .as_ref()
+    .map(|needle| longest_common_substring(needle, event.message()))
+    .unwrap_or(0);
+
+  (similarity, criteria)
+}
+
+/// Check that each of the provided expectations is met by at least one
+/// captured event, panicking if that is not the case.
+pub(crate) fn expectations(captured: &Captured, expectations: &[Query]) {
+  let events = captured.events();
+
+  for query in expectations {
+    if events.iter().any(|event| query.matches(event)) {
+      continue
+    }
+
+    let mut message =
+      format!("test-log: expected an event matching `{query}`, but none was captured");
+    if events.is_empty() {
+      message.push_str("; no events were captured at all");
+    } else {
+      let mut nearest = events.iter().collect::<Vec<_>>();
+      nearest.sort_by_key(|event| Reverse(closeness(query, event)));
+
+      message.push_str("\nnearest captured events:");
+      for event in nearest.into_iter().take(MAX_NEAREST) {
+        let _result = write!(message, "\n  {event}");
+      }
+    }
+    panic!("{message}");
+  }
+}
+
+
+/// Configuration for failing a test that emitted events at or above a
+/// certain level.
+#[derive(Debug)]
+pub(crate) struct Deny {
+  /// The level at or above which events are denied.
+  pub(crate) level: Level,
+  /// Targets (along with nested ones) exempt from the check.
+  pub(crate) exempt: Vec<String>,
+}
+
+impl Deny {
+  /// Parse the level and comma separated list of exempt targets.
+  ///
+  /// # Panics
+  /// This function panics if `level` is not a valid level.
+  pub(crate) fn parse(level: &str, exempt: Option<&str>) -> Self {
+    let level = level
+      .trim()
+      .parse()
+      .unwrap_or_else(|err| panic!("test-log: deny level must be valid: {err}"));
+    let exempt = exempt
+      .unwrap_or_default()
+      .split(',')
+      .map(str::trim)
+      .filter(|target| !target.is_empty())
+      .map(str::to_string)
+      .collect();
+
+    Self { level, exempt }
+  }
+
+  /// Check whether the given event is denied.
+  fn denies(&self, event: &Event) -> bool {
+    event.level() <= self.level
+      && !self
+        .exempt
+        .iter()
+        .any(|target| Query::new().target(target.as_str()).matches(event))
+  }
+}
+
+
+/// Check that no captured event is denied, panicking if that is not the
+/// case.
+pub(crate) fn deny(captured: &Captured, deny: &Deny) {
+  let events = captured.events();
+  let denied = events
+    .iter()
+    .filter(|event| deny.denies(event))
+    .collect::<Vec<_>>();
+
+  if !denied.is_empty() {
+    let mut message = format!(
+      "test-log: {} event(s) at or above level `{}` were emitted:",
+      denied.len(),
+      deny.level
+    );
+    for event in denied {
+      let _result = write!(message, "\n  {event}");
+    }
+    panic!("{message}");
+  }
+}
@@ -0,0 +1,178 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: (Apache-2.0 OR MIT)
+
+//! The configuration of a test, as emitted by the `#[test]` attribute.
+//!
+//! Instead of generating the code for setting up logging and tracing
+//! infrastructure for each test, the attribute emits a constant
+//! [`Config`] and a call to [`init`], reducing the amount of code the
+//! compiler has to deal with considerably for large test suites.
+
+use std::sync::Once;
+
+#[cfg(feature = "trace")]
+use tracing_subscriber::fmt;
+
+use crate::capture::Level;
+use crate::capture::Query;
+#[cfg(feature = "trace")]
+use crate::filter::directives;
+#[cfg(feature = "trace")]
+use crate::format::JsonFields;
+#[cfg(feature = "trace")]
+use crate::format::JsonFormat;
+#[cfg(feature = "trace")]
+use crate::output::span_events;
+#[cfg(feature = "trace")]
+use crate::output::Format;
+use crate::scope::Scope;
+use crate::scope::ScopeGuard;
+
+
+/// An expectation on the events emitted by a test, as specified via
+/// `expect_log(...)`.
+#[derive(Debug)]
+pub struct Expectation<'config> {
+  /// The level of the expected event.
+  pub level: Option<Level>,
+  /// The target (prefix) of the expected event.
+  pub target: Option<&'config str>,
+  /// A substring of the expected event's message.
+  pub contains: Option<&'config str>,
+}
+
+impl Expectation<'_> {
+  /// Convert the expectation into the equivalent query.
+  fn to_query(&self) -> Query {
+    let mut query = Query::new();
+    if let Some(level) = self.level {
+      query = query.level(level);
+    }
+    if let Some(target) = self.target {
+      query = query.target(target);
+    }
+    if let Some(contains) = self.contains {
+      query = query.contains(contains);
+    }
+    query
+  }
+}
+
+
+/// The configuration of a single test.
+///
+/// All values have been validated by the procedural macro already, but
+/// ones from the environment may still be invalid, causing a panic.
+#[derive(Debug)]
+pub struct Config<'config> {
+  /// The fully qualified name of the test.
+  pub name: Option<&'config str>,
+  /// The filter to use, subject to `RUST_LOG`.
+  pub filter: &'config str,
+  /// The mode determining when the test's output is emitted.
+  pub capture: Option<&'config str>,
+  /// Whether to color output.
+  pub color: Option<&'config str>,
+  /// The format in which output is emitted.
+  pub format: Option<&'config str>,
+  /// Whether to write output to a per-test log file as well.
+  pub log_files: Option<&'config str>,
+  /// The name of the environment variable to use instead of `RUST_LOG`.
+  pub env_var: Option<&'config str>,
+  /// The policy for combining `RUST_LOG` with the filter.
+  pub env_policy: Option<&'config str>,
+  /// Comma separated synthesized span lifecycle events to emit.
+  pub span_events: Option<&'config str>,
+  /// The name of the variable to use instead of `RUST_LOG_SPAN_EVENTS`.
+  pub span_events_env: Option<&'config str>,
+  /// Expectations on the events emitted by the test.
+  pub expect_log: &'config [Expectation<'config>],
+  /// The level at or above which events cause the test to fail.
+  pub deny_level: Option<&'config str>,
+  /// Comma separated targets exempt from `deny_level`.
+  pub deny_exempt: Option<&'config str>,
+}
+
+impl Config<'_> {
+  /// The default configuration, used for everything not specified
+  /// otherwise.
+  pub const DEFAULT: Config<'static> = Config {
+    name: None,
+    filter: "info",
+    capture: None,
+    color: None,
+    format: None,
+    log_files: None,
+    env_var: None,
+    env_policy: None,
+    span_events: None,
+    span_events_env: None,
+    expect_log: &[],
+    deny_level: None,
+    deny_exempt: None,
+  };
+
+  /// Configure logging and tracing infrastructure of `scope`.
+  pub(crate) fn configure(&self, scope: Scope) -> Scope {
+    let scope = scope
+      .name(self.name)
+      .color(self.color)
+      .format(self.format)
+      .log_files(self.log_files)
+      .env_var(self.env_var)
+      .env_policy(self.env_policy);
+
+    #[cfg(all(feature = "log", not(feature = "trace")))]
+    let scope = {
+      let logger = scope.log_builder(self.filter).build();
+      scope.with_logger(logger)
+    };
+
+    #[cfg(feature = "trace")]
+    let scope = {
+      // The filter only applies to the output, not to the events
+      // captured for the test.
+      let filter = directives(&scope.filter(self.filter));
+      let layer = fmt::layer()
+        .with_span_events(span_events(self.span_events, self.span_events_env))
+        .with_writer(scope.make_writer())
+        .with_ansi(scope.ansi());
+      match scope.event_format() {
+        None | Some(Format::Full) => scope.with_layer(layer, filter),
+        Some(Format::Compact) => scope.with_layer(layer.compact(), filter),
+        Some(Format::Pretty) => scope.with_layer(layer.pretty(), filter),
+        Some(Format::Json) => {
+          let format = JsonFormat::new(scope.test_name());
+          let layer = layer.fmt_fields(JsonFields).event_format(format);
+          scope.with_layer(layer, filter)
+        },
+      }
+    };
+
+    scope
+  }
+}
+
+
+/// Set up logging and tracing for a test as per `config`, returning
+/// the guard for the test's scope.
+///
+/// # Panics
+/// This function panics if any part of the configuration, including
+/// values from the environment overriding it, is invalid.
+pub fn init(config: &Config<'_>) -> ScopeGuard {
+  static FALLBACK: Once = Once::new();
+
+  // Threads not associated with any test (e.g., ones spawned by a test
+  // itself) use the default configuration, without any of the test
+  // specific output.
+  let () = FALLBACK.call_once(|| Config::DEFAULT.configure(Scope::new()).install());
+
+  let scope = Scope::new().capture(config.capture);
+  let scope = config.configure(scope);
+  let scope = config.expect_log.iter().fold(scope, |scope, expectation| {
+    scope.expect_log(expectation.to_query())
+  });
+  let scope = scope.deny_level(config.deny_level, config.deny_exempt);
+  scope.start()
+}
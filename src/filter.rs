@@ -0,0 +1,150 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: (Apache-2.0 OR MIT)
+
+//! Construction of the log filter for a test.
+
+#[cfg(any(feature = "log", feature = "trace"))]
+use std::env;
+
+#[cfg(feature = "trace")]
+use tracing_subscriber::filter::Directive;
+#[cfg(feature = "trace")]
+use tracing_subscriber::EnvFilter;
+
+
+/// The name of the environment variable providing filter directives,
+/// unless configured otherwise.
+#[cfg(any(feature = "log", feature = "trace"))]
+pub(crate) const FILTER_ENV: &str = "RUST_LOG";
+
+
+/// The policy for combining the `RUST_LOG` environment variable with a
+/// test's filter.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum EnvPolicy {
+  /// `RUST_LOG`, if set, replaces the test's filter.
+  #[default]
+  Override,
+  /// Directives from `RUST_LOG` are added to the test's filter, taking
+  /// precedence for the targets they cover.
+  Merge,
+  /// `RUST_LOG` is ignored.
+  Ignore,
+}
+
+impl EnvPolicy {
+  /// Parse a policy from its name.
+  ///
+  /// # Panics
+  /// This function panics if `policy` is not a valid policy.
+  pub(crate) fn parse(policy: &str) -> Self {
+    match policy {
+      "override" => Self::Override,
+      "merge" => Self::Merge,
+      "ignore" => Self::Ignore,
+      _ => panic!(
+        "test-log: environment policy must be one of `override`, `merge`, or `ignore`\n\t\
+         Got: {policy}"
+      ),
+    }
+  }
+}
+
+
+/// Split a filter specification into its directives and an optional
+/// `env_logger` style `/regex` message filter.
+#[cfg(any(feature = "log", feature = "trace"))]
+fn split_regex(spec: &str) -> (&str, Option<&str>) {
+  match spec.split_once('/') {
+    Some((directives, regex)) => (directives, Some(regex)),
+    None => (spec, None),
+  }
+}
+
+/// Combine the `default` filter with the one provided via the
+/// environment variable `var`, as per `policy`.
+#[cfg(any(feature = "log", feature = "trace"))]
+pub(crate) fn combine(default: &str, var: &str, policy: EnvPolicy) -> String {
+  let env = env::var(var).unwrap_or_default();
+  let env = env.trim();
+  if env.is_empty() {
+    return default.to_string()
+  }
+
+  match policy {
+    EnvPolicy::Override => env.to_string(),
+    EnvPolicy::Ignore => default.to_string(),
+    EnvPolicy::Merge => {
+      let (default, default_regex) = split_regex(default);
+      let (env, env_regex) = split_regex(env);
+      // Later directives for the same target take precedence with both
+      // backends, so `RUST_LOG` has to come last.
+      let mut spec = [default, env]
+        .into_iter()
+        .filter(|directives| !directives.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join(",");
+      if let Some(regex) = env_regex.or(default_regex) {
+        spec.push('/');
+        spec.push_str(regex);
+      }
+      spec
+    },
+  }
+}
+
+
+/// Split a list of directives at commas not enclosed in brackets, so
+/// that span filters with multiple fields (e.g., `[span{a=1,b}]`) stay
+/// intact.
+#[cfg(any(feature = "log", feature = "trace"))]
+fn split_directives(spec: &str) -> impl Iterator<Item = &str> {
+  let mut depth = 0usize;
+  spec
+    .split(move |c| {
+      match c {
+        '[' => depth += 1,
+        ']' => depth = depth.saturating_sub(1),
+        _ => (),
+      }
+      c == ',' && depth == 0
+    })
+    .map(str::trim)
+    .filter(|directive| !directive.is_empty())
+}
+
+/// Remove span directives (e.g., `[request{id=5}]=trace`) from the
+/// provided directives, as `env_logger` does not support them.
+#[cfg(all(feature = "log", not(feature = "trace")))]
+pub(crate) fn log_directives(spec: &str) -> String {
+  split_directives(spec)
+    .filter(|directive| !directive.contains('['))
+    .collect::<Vec<_>>()
+    .join(",")
+}
+
+/// Parse the provided directives, as retrieved via
+/// [`Scope::filter`][crate::scope::Scope::filter].
+///
+/// Invalid directives are reported and ignored.
+#[cfg(feature = "trace")]
+pub(crate) fn directives(spec: &str) -> Vec<Directive> {
+  split_directives(spec)
+    .filter_map(|directive| match directive.parse::<Directive>() {
+      Ok(directive) => Some(directive),
+      Err(err) => {
+        eprintln!("test-log: ignoring `{directive}`: {err}");
+        None
+      },
+    })
+    .collect()
+}
+
+/// Create the `tracing` filter for the provided directives.
+#[cfg(feature = "trace")]
+pub(crate) fn env_filter(directives: &[Directive]) -> EnvFilter {
+  directives.iter().cloned().fold(
+    EnvFilter::builder().parse_lossy(""),
+    EnvFilter::add_directive,
+  )
+}
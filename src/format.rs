@@ -0,0 +1,362 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: (Apache-2.0 OR MIT)
+
+//! Formatting of log records and tracing events in the formats
+//! selectable via `format`.
+//!
+//! The `tracing` backend relies on the formatters of
+//! `tracing-subscriber` for all but the JSON format. Records emitted
+//! with the `log` backend are written in a way mimicking these
+//! formatters, so that output looks the same regardless of the backend
+//! in use.
+
+#[cfg(feature = "trace")]
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+#[cfg(all(feature = "log", not(feature = "trace")))]
+use std::io;
+#[cfg(all(feature = "log", not(feature = "trace")))]
+use std::io::Write as _;
+#[cfg(feature = "trace")]
+use std::mem;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+#[cfg(all(feature = "log", not(feature = "trace")))]
+use env_logger::fmt::Formatter as LogFormatter;
+
+#[cfg(all(feature = "log", not(feature = "trace")))]
+use logging::Record;
+
+#[cfg(feature = "trace")]
+use tracing::field::Field;
+#[cfg(feature = "trace")]
+use tracing::field::Visit;
+#[cfg(feature = "trace")]
+use tracing::span::Record as SpanRecord;
+#[cfg(feature = "trace")]
+use tracing::Subscriber;
+#[cfg(feature = "trace")]
+use tracing_subscriber::field::RecordFields;
+#[cfg(feature = "trace")]
+use tracing_subscriber::fmt::format::Writer;
+#[cfg(feature = "trace")]
+use tracing_subscriber::fmt::FmtContext;
+#[cfg(feature = "trace")]
+use tracing_subscriber::fmt::FormatEvent;
+#[cfg(feature = "trace")]
+use tracing_subscriber::fmt::FormatFields;
+#[cfg(feature = "trace")]
+use tracing_subscriber::fmt::FormattedFields;
+#[cfg(feature = "trace")]
+use tracing_subscriber::registry::LookupSpan;
+#[cfg(feature = "trace")]
+use tracing_subscriber::registry::Scope as SpanScope;
+
+use crate::capture::Event;
+#[cfg(all(feature = "log", not(feature = "trace")))]
+use crate::output::Format;
+
+
+/// A point in time, displayed in RFC 3339 format with microsecond
+/// precision, in UTC.
+struct Timestamp(SystemTime);
+
+impl Display for Timestamp {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    let since_epoch = self.0.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (days, secs) = (secs / 86400, secs % 86400);
+
+    // Convert the number of days since the epoch into a civil date, as
+    // per http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = days + 719468;
+    let era = days / 146097;
+    let doe = days % 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    write!(
+      f,
+      "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:06}Z",
+      secs / 3600,
+      secs % 3600 / 60,
+      secs % 60,
+      since_epoch.subsec_micros(),
+    )
+  }
+}
+
+
+/// A string, displayed as a quoted and escaped JSON string.
+struct JsonStr<'s>(&'s str);
+
+impl Display for JsonStr<'_> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    f.write_str("\"")?;
+    for c in self.0.chars() {
+      match c {
+        '"' => f.write_str("\\\"")?,
+        '\\' => f.write_str("\\\\")?,
+        '\n' => f.write_str("\\n")?,
+        '\r' => f.write_str("\\r")?,
+        '\t' => f.write_str("\\t")?,
+        c if c.is_control() => write!(f, "\\u{:04x}", u32::from(c))?,
+        c => write!(f, "{c}")?,
+      }
+    }
+    f.write_str("\"")
+  }
+}
+
+
+/// An optional value, displayed as JSON `null` if absent.
+struct JsonOpt<T>(Option<T>);
+
+impl<T> Display for JsonOpt<T>
+where
+  T: Display,
+{
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    match &self.0 {
+      Some(value) => value.fmt(f),
+      None => f.write_str("null"),
+    }
+  }
+}
+
+
+/// An event, displayed as a single line JSON object as per the schema
+/// documented at the crate root.
+struct Json<'e> {
+  /// The fully qualified name of the test emitting the event.
+  test: Option<&'e str>,
+  /// The event to display.
+  event: &'e Event,
+  /// The names of the spans the event was emitted in, starting with the
+  /// outermost one, along with their fields formatted as the members
+  /// of a JSON object.
+  spans: &'e [(&'e str, String)],
+  /// The time at which the event was emitted.
+  time: SystemTime,
+}
+
+impl Display for Json<'_> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    let Self {
+      test,
+      event,
+      spans,
+      time,
+    } = self;
+
+    write!(
+      f,
+      "{{\"timestamp\":\"{}\",\"test\":{},\"level\":{},\"target\":{},\
+       \"module_path\":{},\"file\":{},\"line\":{},\"fields\":{{\"message\":{}",
+      Timestamp(*time),
+      JsonOpt(test.map(JsonStr)),
+      JsonStr(&event.level().as_str().to_ascii_uppercase()),
+      JsonStr(event.target()),
+      JsonOpt(event.module_path().map(JsonStr)),
+      JsonOpt(event.file().map(JsonStr)),
+      JsonOpt(event.line()),
+      JsonStr(event.message()),
+    )?;
+    for (name, value) in event.fields() {
+      write!(f, ",{}:{}", JsonStr(name), JsonStr(value))?;
+    }
+    f.write_str("},\"spans\":[")?;
+    for (i, (name, fields)) in spans.iter().enumerate() {
+      let sep = if i == 0 { "" } else { "," };
+      write!(
+        f,
+        "{sep}{{\"name\":{},\"fields\":{{{fields}}}}}",
+        JsonStr(name)
+      )?;
+    }
+    f.write_str("]}")
+  }
+}
+
+
+/// Write a `log` record emitted by the test with name `test` in the
+/// given format.
+#[cfg(all(feature = "log", not(feature = "trace")))]
+pub(crate) fn write_record(
+  format: Format,
+  test: Option<&str>,
+  buf: &mut LogFormatter,
+  record: &Record<'_>,
+) -> io::Result<()> {
+  let time = SystemTime::now();
+  let event = Event::from(record);
+  // The level is padded before coloring, as escape sequences would
+  // count towards its width otherwise.
+  let level = format!("{:>5}", event.level().as_str().to_ascii_uppercase());
+  #[cfg(feature = "color")]
+  let style = buf.default_level_style(record.level());
+  #[cfg(feature = "color")]
+  let level = format!("{style}{level}{style:#}");
+
+  match format {
+    Format::Full | Format::Compact => {
+      write!(
+        buf,
+        "{} {level} {}: {}",
+        Timestamp(time),
+        event.target(),
+        event.message()
+      )?;
+      for (name, value) in event.fields() {
+        write!(buf, " {name}={value}")?;
+      }
+      writeln!(buf)
+    },
+    Format::Pretty => {
+      write!(
+        buf,
+        "  {} {level} {}: {}",
+        Timestamp(time),
+        event.target(),
+        event.message()
+      )?;
+      for (name, value) in event.fields() {
+        write!(buf, ", {name}: {value}")?;
+      }
+      writeln!(buf)?;
+      if let (Some(file), Some(line)) = (event.file(), event.line()) {
+        writeln!(buf, "    at {file}:{line}")?;
+      }
+      writeln!(buf)
+    },
+    Format::Json => {
+      let json = Json {
+        test,
+        event: &event,
+        spans: &[],
+        time,
+      };
+      writeln!(buf, "{json}")
+    },
+  }
+}
+
+
+/// A visitor formatting the fields it visits as the members of a JSON
+/// object.
+#[cfg(feature = "trace")]
+struct JsonVisitor(String);
+
+#[cfg(feature = "trace")]
+impl JsonVisitor {
+  /// Add a member with the given `name` and `value`.
+  fn push(&mut self, name: &str, value: &str) {
+    if !self.0.is_empty() {
+      self.0.push(',');
+    }
+    self.0 += &format!("{}:{}", JsonStr(name), JsonStr(value));
+  }
+}
+
+#[cfg(feature = "trace")]
+impl Visit for JsonVisitor {
+  fn record_str(&mut self, field: &Field, value: &str) {
+    self.push(field.name(), value)
+  }
+
+  fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+    self.push(field.name(), &format!("{value:?}"))
+  }
+}
+
+
+/// A `tracing-subscriber` field formatter formatting the fields of
+/// spans as the members of a JSON object.
+#[cfg(feature = "trace")]
+#[derive(Debug)]
+pub(crate) struct JsonFields;
+
+#[cfg(feature = "trace")]
+impl<'writer> FormatFields<'writer> for JsonFields {
+  fn format_fields<R>(&self, mut writer: Writer<'writer>, fields: R) -> FmtResult
+  where
+    R: RecordFields,
+  {
+    let mut visitor = JsonVisitor(String::new());
+    fields.record(&mut visitor);
+    writer.write_str(&visitor.0)
+  }
+
+  fn add_fields(
+    &self,
+    current: &'writer mut FormattedFields<Self>,
+    fields: &SpanRecord<'_>,
+  ) -> FmtResult {
+    let mut visitor = JsonVisitor(mem::take(&mut current.fields));
+    fields.record(&mut visitor);
+    current.fields = visitor.0;
+    Ok(())
+  }
+}
+
+
+/// A `tracing-subscriber` event formatter emitting each event as a
+/// single line JSON object.
+#[cfg(feature = "trace")]
+#[derive(Debug)]
+pub(crate) struct JsonFormat {
+  /// The fully qualified name of the test emitting events.
+  test: Option<String>,
+}
+
+#[cfg(feature = "trace")]
+impl JsonFormat {
+  /// Create a new `JsonFormat` for the test with the given name.
+  pub(crate) fn new(test: Option<&str>) -> Self {
+    Self {
+      test: test.map(str::to_string),
+    }
+  }
+}
+
+#[cfg(feature = "trace")]
+impl<S> FormatEvent<S, JsonFields> for JsonFormat
+where
+  S: Subscriber + for<'a> LookupSpan<'a>,
+{
+  fn format_event(
+    &self,
+    ctx: &FmtContext<'_, S, JsonFields>,
+    mut writer: Writer<'_>,
+    event: &tracing::Event<'_>,
+  ) -> FmtResult {
+    let spans = ctx
+      .event_scope()
+      .into_iter()
+      .flat_map(SpanScope::from_root)
+      .map(|span| {
+        let fields = span
+          .extensions()
+          .get::<FormattedFields<JsonFields>>()
+          .map(|fields| fields.fields.clone())
+          .unwrap_or_default();
+        (span.name(), fields)
+      })
+      .collect::<Vec<_>>();
+
+    let json = Json {
+      test: self.test.as_deref(),
+      event: &Event::from(event),
+      spans: &spans,
+      time: SystemTime::now(),
+    };
+    writeln!(writer, "{json}")
+  }
+}
@@ -0,0 +1,142 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: (Apache-2.0 OR MIT)
+
+//! Initialization of logging and tracing infrastructure outside of the
+//! `#[test]` attribute.
+
+use crate::config::Config;
+use crate::scope::Scope;
+
+
+/// Initialize logging and/or tracing (depending on the features used)
+/// with the default configuration, just like the
+/// [`test`][macro@crate::test] attribute does.
+///
+/// This function is meant for contexts in which an attribute cannot be
+/// used, such as doctests, tests with `harness = false`, and
+/// benchmarks. Use [`Builder`] to customize the configuration.
+///
+/// Initialization happens once per process; subsequent calls have no
+/// effect.
+///
+/// ```rust
+/// test_log::init();
+///
+/// # #[cfg(feature = "log")]
+/// logging::info!("logged as usual");
+/// ```
+pub fn init() {
+  Builder::new().init()
+}
+
+
+/// A builder for configuring the logging and tracing infrastructure
+/// initialized by [`init`][Builder::init].
+///
+/// The available settings correspond to the arguments of the
+/// [`test`][macro@crate::test] attribute of the same name.
+///
+/// ```rust
+/// test_log::Builder::new()
+///   .filter("info,my_crate=debug")
+///   .env_policy("merge")
+///   .init();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Builder {
+  /// The filter to use if not overridden via `RUST_LOG`.
+  filter: Option<String>,
+  /// The name of the environment variable to use instead of `RUST_LOG`.
+  env_var: Option<String>,
+  /// The policy for combining `RUST_LOG` with the filter.
+  env_policy: Option<String>,
+  /// Comma separated synthesized span lifecycle events to emit.
+  span_events: Option<String>,
+  /// The name of the variable to use instead of `RUST_LOG_SPAN_EVENTS`.
+  span_events_env: Option<String>,
+  /// Whether to color output.
+  color: Option<String>,
+  /// The format in which output is emitted.
+  format: Option<String>,
+}
+
+impl Builder {
+  /// Create a new `Builder` with the default configuration.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Set the filter directives to use if `RUST_LOG` is not set (the
+  /// default is `info`).
+  pub fn filter(mut self, filter: &str) -> Self {
+    self.filter = Some(filter.to_string());
+    self
+  }
+
+  /// Set the name of the environment variable to read filter directives
+  /// from instead of `RUST_LOG`.
+  pub fn env_var(mut self, var: &str) -> Self {
+    self.env_var = Some(var.to_string());
+    self
+  }
+
+  /// Set the policy for combining `RUST_LOG` with the filter, one of
+  /// `override` (the default), `merge`, or `ignore`.
+  pub fn env_policy(mut self, policy: &str) -> Self {
+    self.env_policy = Some(policy.to_string());
+    self
+  }
+
+  /// Set the comma separated synthesized span lifecycle events to emit,
+  /// unless overridden via `RUST_LOG_SPAN_EVENTS`.
+  pub fn span_events(mut self, events: &str) -> Self {
+    self.span_events = Some(events.to_string());
+    self
+  }
+
+  /// Set the name of the environment variable to read span events from
+  /// instead of `RUST_LOG_SPAN_EVENTS`.
+  pub fn span_events_env(mut self, var: &str) -> Self {
+    self.span_events_env = Some(var.to_string());
+    self
+  }
+
+  /// Set whether to color output, one of `auto` (the default),
+  /// `always`, or `never`.
+  pub fn color(mut self, color: &str) -> Self {
+    self.color = Some(color.to_string());
+    self
+  }
+
+  /// Set the format in which output is emitted, one of `full`,
+  /// `compact`, `pretty`, or `json`, unless overridden via
+  /// `RUST_LOG_FORMAT`.
+  pub fn format(mut self, format: &str) -> Self {
+    self.format = Some(format.to_string());
+    self
+  }
+
+  /// Initialize logging and/or tracing with the configuration at hand.
+  ///
+  /// Initialization happens once per process; subsequent calls have no
+  /// effect.
+  ///
+  /// # Panics
+  /// This method panics if any of the settings, or the environment
+  /// variables overriding them, are invalid.
+  pub fn init(self) {
+    let config = Config {
+      filter: self.filter.as_deref().unwrap_or(Config::DEFAULT.filter),
+      color: self.color.as_deref(),
+      format: self.format.as_deref(),
+      env_var: self.env_var.as_deref(),
+      env_policy: self.env_policy.as_deref(),
+      span_events: self.span_events.as_deref(),
+      span_events_env: self.span_events_env.as_deref(),
+      ..Config::DEFAULT
+    };
+    // Output is not captured, as there is no test that could fail and
+    // cause it to be emitted.
+    let () = config.configure(Scope::new()).install();
+  }
+}
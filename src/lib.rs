@@ -7,6 +7,57 @@
 //! A crate providing a replacement #[[macro@test]] attribute that
 //! initializes logging and/or tracing infrastructure before running
 //! tests.
+//!
+//! # JSON Output
+//! With the `json` format selected, via the `format` argument of the
+//! [`test`][macro@test] attribute or the `RUST_LOG_FORMAT` environment
+//! variable, each `log` record and `tracing` event is emitted as a
+//! single line JSON object, e.g.:
+//! ```json
+//! {"timestamp":"2026-01-02T03:04:05.678901Z","test":"my_crate::tests::it_works","level":"INFO","target":"my_crate::db","module_path":"my_crate::db","file":"src/db.rs","line":42,"fields":{"message":"connected","attempt":"2"},"spans":[{"name":"my_crate::tests::it_works","fields":{}},{"name":"connect","fields":{"host":"localhost"}}]}
+//! ```
+//!
+//! Objects adhere to the following schema. Members are neither removed
+//! nor changed in meaning in semver compatible releases, but new ones
+//! may be added.
+//!
+//! | Member        | Type             | Description |
+//! |---------------|------------------|-------------|
+//! | `timestamp`   | string           | The time the event was emitted at, in RFC 3339 format with microsecond precision, in UTC |
+//! | `test`        | string or `null` | The fully qualified name of the test emitting the event, `null` if emitted outside of a test, e.g., after [`init()`] |
+//! | `level`       | string           | One of `ERROR`, `WARN`, `INFO`, `DEBUG`, or `TRACE` |
+//! | `target`      | string           | The target of the event |
+//! | `module_path` | string or `null` | The path of the module the event was emitted in |
+//! | `file`        | string or `null` | The source file the event was emitted in |
+//! | `line`        | number or `null` | The line in `file` the event was emitted at |
+//! | `fields`      | object           | The `message` of the event along with all of its other fields |
+//! | `spans`       | array            | The spans the event was emitted in, starting with the outermost one, as objects with a `name` and an object of `fields` |
+//!
+//! Field values are always strings, formatted via `Debug` unless
+//! recorded as strings in the first place. With the `trace` feature
+//! enabled, the outermost span is the one named after the test; with
+//! only the `log` feature enabled, `spans` is always empty.
+
+mod capture;
+mod check;
+mod config;
+mod filter;
+#[cfg(any(feature = "log", feature = "trace"))]
+mod format;
+mod init;
+#[cfg(all(feature = "log", not(feature = "trace")))]
+mod logger;
+mod outcome;
+mod output;
+mod scope;
+
+pub use capture::captured;
+pub use capture::Captured;
+pub use capture::Event;
+pub use capture::Level;
+pub use capture::Query;
+pub use init::init;
+pub use init::Builder;
 
 /// A procedural macro for the `test` attribute.
 ///
@@ -65,8 +116,51 @@
 ///   // ...
 /// }
 /// ```
+///
+/// Configuration can be provided directly in the attribute, in the form
+/// of comma separated `key = value` pairs, optionally alongside a
+/// wrapped `#[test]` attribute:
+/// ```rust
+/// use test_log::test;
+///
+/// #[test(tokio::test, filter = "debug")]
+/// async fn it_works_verbosely() {
+///   // ...
+/// }
+/// ```
 pub use test_log_macros::test;
 
+/// A procedural macro for applying the [`test`][macro@test] attribute to
+/// all tests of a module.
+///
+/// The attribute is applied to an inline module and rewrites every
+/// function in it that carries a test attribute, be it `#[test]` or a
+/// known third-party one such as `#[tokio::test]`, `#[rstest]`, or
+/// `#[test_case(...)]`, as if it was annotated with
+/// `#[test_log::test]`. Configuration provided to the attribute (or via
+/// `#[test_log(...)]` on the module) is inherited by each test, unless
+/// overridden by the test itself:
+/// ```rust
+/// #[test_log::test_module(filter = "debug")]
+/// mod tests {
+///   #[test]
+///   fn it_works() {
+///     // ...
+///   }
+///
+///   #[tokio::test]
+///   async fn it_works_asynchronously() {
+///     // ...
+///   }
+///
+///   #[test_log::test(filter = "trace")]
+///   fn it_works_verbosely() {
+///     // ...
+///   }
+/// }
+/// ```
+pub use test_log_macros::test_module;
+
 #[cfg(feature = "trace")]
 #[doc(hidden)]
 pub use tracing_subscriber;
@@ -74,3 +168,20 @@ pub use tracing_subscriber;
 #[cfg(feature = "log")]
 #[doc(hidden)]
 pub use env_logger;
+
+/// Functionality used by code generated by the procedural macro. Not
+/// part of the public API.
+#[doc(hidden)]
+pub mod __private {
+  pub use crate::config::init;
+  pub use crate::config::Config;
+  pub use crate::config::Expectation;
+  pub use crate::outcome::AnyOutcome;
+  pub use crate::outcome::Outcome;
+  pub use crate::outcome::ResultOutcome;
+  pub use crate::scope::Entered;
+  pub use crate::scope::ScopeGuard;
+  pub use crate::scope::Scoped;
+  #[cfg(feature = "trace")]
+  pub use tracing;
+}
@@ -0,0 +1,115 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: (Apache-2.0 OR MIT)
+
+//! A process-wide `log` logger routing records to the logger of the
+//! currently running test.
+
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::OnceLock;
+use std::sync::PoisonError;
+
+use env_logger::Logger;
+
+use logging::LevelFilter;
+use logging::Log;
+use logging::Metadata;
+use logging::Record;
+
+use crate::capture::Level;
+use crate::scope;
+use crate::scope::Shared;
+
+
+/// The logger used for records emitted outside of any test's scope.
+static FALLBACK: OnceLock<Arc<Logger>> = OnceLock::new();
+
+
+/// Check whether records at `level` are captured in the given scope,
+/// regardless of whether they pass the test's filter.
+fn captures(shared: Option<&Shared>, level: logging::Level) -> bool {
+  shared
+    .and_then(|shared| shared.capture_level)
+    .is_some_and(|capture| Level::from(level) <= capture)
+}
+
+
+/// A `log::Log` implementation dispatching to the logger of the test
+/// whose scope is entered on the current thread.
+#[derive(Debug)]
+struct Router;
+
+impl Router {
+  /// Retrieve the logger to use for records emitted in the given
+  /// scope.
+  fn logger(shared: Option<&Shared>) -> Option<&Logger> {
+    shared
+      .and_then(|shared| shared.logger.as_deref())
+      .or_else(|| FALLBACK.get().map(Arc::as_ref))
+  }
+}
+
+impl Log for Router {
+  fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+    let shared = scope::current();
+    let enabled = Self::logger(shared.as_deref())
+      .map(|logger| logger.enabled(metadata))
+      .unwrap_or(false);
+    enabled || captures(shared.as_deref(), metadata.level())
+  }
+
+  fn log(&self, record: &Record<'_>) {
+    let shared = scope::current();
+    let logger = Self::logger(shared.as_deref()).filter(|logger| logger.matches(record));
+    // Records not passing the test's filter are only captured as far
+    // as needed for checking its expectations and denied records.
+    if let Some(shared) = &shared {
+      if logger.is_some() || captures(Some(shared), record.level()) {
+        shared.captured.push(record.into());
+      }
+    }
+    if let Some(logger) = logger {
+      logger.log(record);
+    }
+  }
+
+  fn flush(&self) {
+    let shared = scope::current();
+    if let Some(logger) = Self::logger(shared.as_deref()) {
+      logger.flush();
+    }
+  }
+}
+
+
+/// Install the routing logger, if that has not happened yet, and make
+/// sure that records down to `level` are passed on to it.
+///
+/// The first `fallback` logger provided is remembered as the one to use
+/// for records emitted from threads not associated with any test.
+pub(crate) fn install(fallback: Option<Logger>, level: LevelFilter) {
+  static MAX_LEVEL: OnceLock<Option<Mutex<LevelFilter>>> = OnceLock::new();
+  static ROUTER: Router = Router;
+
+  if let Some(fallback) = fallback {
+    let _set = FALLBACK.set(Arc::new(fallback));
+  }
+  let max_level = MAX_LEVEL.get_or_init(|| {
+    // If somebody else installed a logger already we have no way of
+    // intercepting records, but that is not something we report.
+    logging::set_logger(&ROUTER)
+      .is_ok()
+      .then(|| Mutex::new(LevelFilter::Off))
+  });
+
+  // Filtering is performed by the individual per-test loggers, but
+  // records none of them is interested in do not have to reach us in
+  // the first place.
+  if let Some(max_level) = max_level {
+    let mut max_level = max_level.lock().unwrap_or_else(PoisonError::into_inner);
+    if level > *max_level {
+      *max_level = level;
+      let () = logging::set_max_level(level);
+    }
+  }
+}
@@ -0,0 +1,46 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: (Apache-2.0 OR MIT)
+
+//! Determination of whether a test failed based on the value returned
+//! by its body.
+//!
+//! Tests may return any type implementing `Termination`, but only for
+//! `Result` can we tell whether the test failed. Instead of requiring
+//! a trait bound on the return type, we use auto-ref based method
+//! resolution: given an `Outcome`, `is_failure` resolves to
+//! [`ResultOutcome::is_failure`] if the wrapped value is a `Result` and
+//! to [`AnyOutcome::is_failure`] otherwise. Both traits have to be in
+//! scope at the call site and the method has to be invoked on a
+//! reference to an `Outcome`, i.e., `(&Outcome(&value)).is_failure()`.
+
+
+/// A wrapper around the value returned by a test's body.
+#[derive(Debug)]
+pub struct Outcome<'value, T>(pub &'value T);
+
+
+/// A trait for checking whether a test returning a `Result` failed.
+pub trait ResultOutcome {
+  /// Check whether the test failed.
+  fn is_failure(&self) -> bool;
+}
+
+impl<T, E> ResultOutcome for Outcome<'_, Result<T, E>> {
+  fn is_failure(&self) -> bool {
+    self.0.is_err()
+  }
+}
+
+
+/// A trait for checking whether a test returning anything but a
+/// `Result` failed, which it never does by virtue of its return value.
+pub trait AnyOutcome {
+  /// Check whether the test failed.
+  fn is_failure(&self) -> bool;
+}
+
+impl<T> AnyOutcome for &Outcome<'_, T> {
+  fn is_failure(&self) -> bool {
+    false
+  }
+}
@@ -0,0 +1,537 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: (Apache-2.0 OR MIT)
+
+//! Handling of the output produced while running a test.
+
+use std::env;
+#[cfg(feature = "chrome-trace")]
+use std::fmt::Debug;
+#[cfg(feature = "chrome-trace")]
+use std::fmt::Formatter;
+#[cfg(feature = "chrome-trace")]
+use std::fmt::Result as FmtResult;
+use std::fs;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io;
+#[cfg(feature = "flame")]
+use std::io::BufWriter;
+use std::io::ErrorKind;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+
+#[cfg(any(feature = "chrome-trace", feature = "flame"))]
+use tracing::Subscriber;
+#[cfg(feature = "chrome-trace")]
+use tracing_chrome::ChromeLayer;
+#[cfg(feature = "chrome-trace")]
+use tracing_chrome::ChromeLayerBuilder;
+#[cfg(feature = "chrome-trace")]
+use tracing_chrome::FlushGuard;
+#[cfg(feature = "flame")]
+use tracing_flame::FlameLayer;
+#[cfg(feature = "flame")]
+use tracing_flame::FlushGuard as FlameFlushGuard;
+#[cfg(feature = "trace")]
+use tracing_subscriber::fmt::format::FmtSpan;
+#[cfg(feature = "trace")]
+use tracing_subscriber::fmt::writer::EitherWriter;
+#[cfg(feature = "trace")]
+use tracing_subscriber::fmt::MakeWriter;
+#[cfg(feature = "trace")]
+use tracing_subscriber::fmt::TestWriter;
+#[cfg(any(feature = "chrome-trace", feature = "flame"))]
+use tracing_subscriber::registry::LookupSpan;
+
+
+/// The name of the environment variable overriding the capture mode.
+const CAPTURE_ENV: &str = "RUST_LOG_CAPTURE";
+/// The name of the environment variable overriding whether to write log
+/// files.
+const LOG_FILES_ENV: &str = "RUST_LOG_FILES";
+/// The name of the directory in cargo's target directory that log files
+/// are written to.
+const LOG_FILES_DIR: &str = "test-logs";
+/// The name of the directory in cargo's target directory that Chrome
+/// trace event files are written to.
+#[cfg(feature = "chrome-trace")]
+const CHROME_TRACE_DIR: &str = "test-traces";
+/// The name of the directory in cargo's target directory that folded
+/// stack files are written to.
+#[cfg(feature = "flame")]
+const FLAME_DIR: &str = "test-flames";
+/// The name of the environment variable overriding the output format.
+const FORMAT_ENV: &str = "RUST_LOG_FORMAT";
+/// The name of the environment variable overriding the span events.
+#[cfg(feature = "trace")]
+const SPAN_EVENTS_ENV: &str = "RUST_LOG_SPAN_EVENTS";
+
+
+/// The mode determining when a test's output is emitted.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum Capture {
+  /// Output is emitted as it is produced, subject to the test's filter.
+  #[default]
+  Off,
+  /// Output is recorded at the `trace` level and only emitted if the
+  /// test fails.
+  OnFailure,
+}
+
+impl Capture {
+  /// Determine the capture mode to use, giving precedence to the
+  /// `RUST_LOG_CAPTURE` environment variable over the provided value.
+  ///
+  /// # Panics
+  /// This function panics if the mode to use is invalid.
+  pub(crate) fn from_env_or(default: Option<&str>) -> Self {
+    let value = env::var(CAPTURE_ENV).ok();
+    let value = match (&value, default) {
+      (Some(value), _) => value.as_str(),
+      (None, Some(default)) => default,
+      (None, None) => return Self::default(),
+    };
+
+    match value.trim().to_ascii_lowercase().as_str() {
+      "off" => Self::Off,
+      "on-failure" => Self::OnFailure,
+      _ => panic!(
+        "test-log: {CAPTURE_ENV} must be one of `off` or `on-failure`\n\t\
+         Got: {value}"
+      ),
+    }
+  }
+}
+
+
+/// Determine whether to write a test's output to a log file, giving
+/// precedence to the `RUST_LOG_FILES` environment variable over the
+/// provided value.
+///
+/// # Panics
+/// This function panics if the value to use is invalid.
+pub(crate) fn log_files_from_env_or(default: Option<&str>) -> bool {
+  let value = env::var(LOG_FILES_ENV).ok();
+  let value = match (&value, default) {
+    (Some(value), _) => value.as_str(),
+    (None, Some(default)) => default,
+    (None, None) => return false,
+  };
+
+  match value.trim().to_ascii_lowercase().as_str() {
+    "off" => false,
+    "on" => true,
+    _ => panic!(
+      "test-log: {LOG_FILES_ENV} must be one of `off` or `on`\n\t\
+       Got: {value}"
+    ),
+  }
+}
+
+
+/// The choice of whether to color a test's output.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum Color {
+  /// Color output unless disabled by the environment.
+  #[default]
+  Auto,
+  /// Always color output.
+  Always,
+  /// Never color output.
+  Never,
+}
+
+impl Color {
+  /// Parse a color choice from its name.
+  ///
+  /// # Panics
+  /// This function panics if `color` is not a valid choice.
+  pub(crate) fn parse(color: &str) -> Self {
+    match color {
+      "auto" => Self::Auto,
+      "always" => Self::Always,
+      "never" => Self::Never,
+      _ => panic!(
+        "test-log: color must be one of `auto`, `always`, or `never`\n\t\
+         Got: {color}"
+      ),
+    }
+  }
+
+  /// Determine whether `tracing` output should contain ANSI escape
+  /// sequences.
+  ///
+  /// In `auto` mode, we follow `tracing-subscriber` in honoring
+  /// `NO_COLOR`.
+  #[cfg(feature = "trace")]
+  pub(crate) fn ansi(self) -> bool {
+    cfg!(feature = "color")
+      && match self {
+        Self::Auto => env::var("NO_COLOR").map_or(true, |value| value.is_empty()),
+        Self::Always => true,
+        Self::Never => false,
+      }
+  }
+
+  /// Retrieve the equivalent `env_logger` write style.
+  #[cfg(all(feature = "log", not(feature = "trace")))]
+  pub(crate) fn write_style(self) -> env_logger::WriteStyle {
+    match self {
+      Self::Auto => env_logger::WriteStyle::Auto,
+      Self::Always => env_logger::WriteStyle::Always,
+      Self::Never => env_logger::WriteStyle::Never,
+    }
+  }
+}
+
+
+/// The format in which log records and tracing events are emitted.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Format {
+  /// The default format of `tracing-subscriber`, with a line per event.
+  Full,
+  /// A more compact variant of [`Format::Full`].
+  Compact,
+  /// A multi-line format meant for human consumption.
+  Pretty,
+  /// A JSON object per line.
+  Json,
+}
+
+impl Format {
+  /// Determine the format to use, giving precedence to the
+  /// `RUST_LOG_FORMAT` environment variable over the provided value.
+  ///
+  /// `None` is returned if neither is set, in which case the backend's
+  /// default format is used.
+  ///
+  /// # Panics
+  /// This function panics if the format to use is invalid.
+  pub(crate) fn from_env_or(default: Option<&str>) -> Option<Self> {
+    let value = env::var(FORMAT_ENV).ok();
+    let value = match (&value, default) {
+      (Some(value), _) => value.as_str(),
+      (None, Some(default)) => default,
+      (None, None) => return None,
+    };
+
+    let format = match value.trim().to_ascii_lowercase().as_str() {
+      "full" => Self::Full,
+      "compact" => Self::Compact,
+      "pretty" => Self::Pretty,
+      "json" => Self::Json,
+      _ => panic!(
+        "test-log: {FORMAT_ENV} must be one of `full`, `compact`, `pretty`, or `json`\n\t\
+         Got: {value}"
+      ),
+    };
+    Some(format)
+  }
+}
+
+
+/// An in-memory buffer recording a test's output.
+#[derive(Clone, Debug, Default)]
+pub struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Buffer {
+  /// Emit the recorded output on `stderr`.
+  pub(crate) fn dump(&self) {
+    let buffer = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+    if !buffer.is_empty() {
+      // Use `eprint!` so that the output is captured by the test
+      // harness along with the remaining output of the test.
+      eprint!("{}", String::from_utf8_lossy(&buffer));
+    }
+  }
+}
+
+impl Write for Buffer {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    self
+      .0
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .extend_from_slice(buf);
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    Ok(())
+  }
+}
+
+
+/// A file that a test's output is written to, in addition to the
+/// regular output.
+///
+/// ANSI escape sequences are stripped from everything written.
+#[derive(Clone, Debug)]
+pub struct LogFile(Arc<Mutex<File>>);
+
+impl LogFile {
+  /// Open the log file for the test with the fully qualified `name`,
+  /// i.e., `<crate>/<module>/<test>.log` in the `test-logs` directory
+  /// of cargo's target directory.
+  ///
+  /// The file of a previous run, if any, is kept as `<test>.log.1`. If
+  /// multiple tests of the same name run within a process, e.g., for
+  /// parametrized tests, they share the file.
+  pub(crate) fn open(name: &str) -> io::Result<Self> {
+    /// The log files opened by the process so far.
+    static OPENED: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+    let path = test_file(LOG_FILES_DIR, name, "log")?;
+
+    let mut opened = OPENED.lock().unwrap_or_else(PoisonError::into_inner);
+    let file = if opened.contains(&path) {
+      OpenOptions::new().append(true).open(&path)?
+    } else {
+      match fs::rename(&path, path.with_extension("log.1")) {
+        Ok(()) => (),
+        Err(err) if err.kind() == ErrorKind::NotFound => (),
+        Err(err) => return Err(err),
+      }
+      let file = File::create(&path)?;
+      opened.push(path);
+      file
+    };
+    Ok(Self(Arc::new(Mutex::new(file))))
+  }
+}
+
+impl Write for LogFile {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    let mut stripped = Vec::with_capacity(buf.len());
+    let mut bytes = buf.iter().copied().peekable();
+    while let Some(byte) = bytes.next() {
+      if byte == b'\x1b' && bytes.peek() == Some(&b'[') {
+        // Skip the control sequence up to and including its final
+        // byte.
+        let _final = bytes.find(|byte| (0x40..=0x7e).contains(byte) && *byte != b'[');
+      } else {
+        stripped.push(byte);
+      }
+    }
+
+    let mut file = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+    let () = file.write_all(&stripped)?;
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    self
+      .0
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .flush()
+  }
+}
+
+
+/// Find cargo's target directory, based on the location of the running
+/// executable.
+fn target_dir() -> Option<PathBuf> {
+  let exe = env::current_exe().ok()?;
+  exe
+    .ancestors()
+    .find(|dir| dir.join("CACHEDIR.TAG").is_file())
+    .map(Path::to_path_buf)
+}
+
+
+/// Determine the path of the file with the given `extension` for the
+/// test with the fully qualified `name`, i.e.,
+/// `<crate>/<module>/<test>.<extension>` in the directory `dir` of
+/// cargo's target directory, creating all parent directories.
+fn test_file(dir: &str, name: &str, extension: &str) -> io::Result<PathBuf> {
+  let mut path = target_dir()
+    .ok_or_else(|| {
+      io::Error::new(
+        ErrorKind::NotFound,
+        "failed to find cargo's target directory",
+      )
+    })?
+    .join(dir);
+  path.extend(name.split("::"));
+  let path = path.with_extension(extension);
+  if let Some(dir) = path.parent() {
+    let () = fs::create_dir_all(dir)?;
+  }
+  Ok(path)
+}
+
+
+/// Create a layer writing the spans and events of the test with the
+/// fully qualified `name` to a Chrome trace event file, i.e.,
+/// `<crate>/<module>/<test>.json` in the `test-traces` directory of
+/// cargo's target directory.
+///
+/// The file is complete once the returned guard is dropped.
+#[cfg(feature = "chrome-trace")]
+pub(crate) fn chrome_layer<S>(name: &str) -> io::Result<(ChromeLayer<S>, ChromeGuard)>
+where
+  S: Subscriber + for<'span> LookupSpan<'span> + Send + Sync,
+{
+  let path = test_file(CHROME_TRACE_DIR, name, "json")?;
+  let file = File::create(path)?;
+  let (layer, guard) = ChromeLayerBuilder::new()
+    .writer(file)
+    .include_args(true)
+    .build();
+  Ok((layer, ChromeGuard { _guard: guard }))
+}
+
+
+/// A guard completing a test's Chrome trace event file once dropped.
+#[cfg(feature = "chrome-trace")]
+pub(crate) struct ChromeGuard {
+  /// The guard of the layer writing the file.
+  _guard: FlushGuard,
+}
+
+#[cfg(feature = "chrome-trace")]
+impl Debug for ChromeGuard {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    f.debug_struct("ChromeGuard").finish()
+  }
+}
+
+
+/// A writer emitting everything on `stderr`, in a way that is captured
+/// by the test harness.
+#[cfg(all(feature = "log", not(feature = "trace")))]
+#[derive(Debug)]
+pub(crate) struct Stderr;
+
+#[cfg(all(feature = "log", not(feature = "trace")))]
+impl Write for Stderr {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    eprint!("{}", String::from_utf8_lossy(buf));
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    Ok(())
+  }
+}
+
+
+/// A writer writing to a test's regular output as well as its log file,
+/// if any.
+#[cfg(any(feature = "log", feature = "trace"))]
+#[derive(Debug)]
+pub struct Tee<W>(pub(crate) W, pub(crate) Option<LogFile>);
+
+#[cfg(any(feature = "log", feature = "trace"))]
+impl<W> Write for Tee<W>
+where
+  W: Write,
+{
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    let () = self.0.write_all(buf)?;
+    if let Some(file) = &mut self.1 {
+      let () = file.write_all(buf)?;
+    }
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    let () = self.0.flush()?;
+    if let Some(file) = &mut self.1 {
+      let () = file.flush()?;
+    }
+    Ok(())
+  }
+}
+
+
+/// A guard flushing a test's folded stack file once dropped.
+#[cfg(feature = "flame")]
+pub(crate) type FlameGuard = FlameFlushGuard<BufWriter<File>>;
+
+/// Create a layer writing the span timings of the test with the fully
+/// qualified `name` to a folded stack file, as consumed by `inferno`
+/// and `flamegraph.pl`, i.e., `<crate>/<module>/<test>.folded` in the
+/// `test-flames` directory of cargo's target directory.
+///
+/// Only time spent in spans is recorded, starting with the test's own.
+#[cfg(feature = "flame")]
+pub(crate) fn flame_layer<S>(name: &str) -> io::Result<(FlameLayer<S, BufWriter<File>>, FlameGuard)>
+where
+  S: Subscriber + for<'span> LookupSpan<'span>,
+{
+  let path = test_file(FLAME_DIR, name, "folded")?;
+  let file = File::create(path)?;
+  let layer = FlameLayer::new(BufWriter::new(file)).with_empty_samples(false);
+  let guard = layer.flush_on_drop();
+  Ok((layer, guard))
+}
+
+
+/// A `MakeWriter` writing either to `stderr`, in a way that is captured
+/// by the test harness, or to a test's output buffer, as well as to the
+/// test's log file, if any.
+#[cfg(feature = "trace")]
+#[derive(Clone, Debug)]
+pub struct Writer(pub(crate) Option<Buffer>, pub(crate) Option<LogFile>);
+
+#[cfg(feature = "trace")]
+impl<'a> MakeWriter<'a> for Writer {
+  type Writer = Tee<EitherWriter<TestWriter, Buffer>>;
+
+  fn make_writer(&'a self) -> Self::Writer {
+    let writer = match &self.0 {
+      None => EitherWriter::A(TestWriter::with_stderr()),
+      Some(buffer) => EitherWriter::B(buffer.clone()),
+    };
+    Tee(writer, self.1.clone())
+  }
+}
+
+
+/// Determine the synthesized span lifecycle events to emit, giving
+/// precedence to the `RUST_LOG_SPAN_EVENTS` environment variable (or
+/// the one named by `var`) over the provided comma separated list of
+/// events.
+///
+/// # Panics
+/// This function panics if the events to use are invalid.
+#[cfg(feature = "trace")]
+pub fn span_events(default: Option<&str>, var: Option<&str>) -> FmtSpan {
+  let var = var.unwrap_or(SPAN_EVENTS_ENV);
+  let value = match env::var_os(var) {
+    Some(mut value) => {
+      value.make_ascii_lowercase();
+      value
+        .into_string()
+        .unwrap_or_else(|_| panic!("test-log: {var} must be valid UTF-8"))
+    },
+    None => match default {
+      Some(default) => default.to_string(),
+      None => return FmtSpan::NONE,
+    },
+  };
+
+  value
+    .split(',')
+    .map(|filter| match filter.trim() {
+      "new" => FmtSpan::NEW,
+      "enter" => FmtSpan::ENTER,
+      "exit" => FmtSpan::EXIT,
+      "close" => FmtSpan::CLOSE,
+      "active" => FmtSpan::ACTIVE,
+      "full" => FmtSpan::FULL,
+      _ => panic!(
+        "test-log: {var} must contain filters separated by `,`.\n\t\
+         For example: `active` or `new,close`\n\t\
+         Supported filters: new, enter, exit, close, active, full\n\t\
+         Got: {value}"
+      ),
+    })
+    .fold(FmtSpan::NONE, |acc, filter| filter | acc)
+}
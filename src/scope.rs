@@ -0,0 +1,685 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: (Apache-2.0 OR MIT)
+
+//! Per-test scoping of logging and tracing infrastructure.
+
+use std::cell::RefCell;
+#[cfg(all(feature = "log", not(feature = "trace")))]
+use std::env;
+#[cfg(feature = "trace")]
+use std::fmt::Debug;
+#[cfg(feature = "trace")]
+use std::fmt::Formatter;
+#[cfg(feature = "trace")]
+use std::fmt::Result as FmtResult;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::Context;
+use std::task::Poll;
+use std::thread;
+
+#[cfg(feature = "trace")]
+use tracing::dispatcher;
+#[cfg(feature = "trace")]
+use tracing::dispatcher::DefaultGuard;
+#[cfg(feature = "trace")]
+use tracing::Dispatch;
+#[cfg(feature = "trace")]
+use tracing_subscriber::filter::Directive;
+#[cfg(feature = "trace")]
+use tracing_subscriber::filter::FilterExt as _;
+#[cfg(feature = "trace")]
+use tracing_subscriber::filter::LevelFilter;
+#[cfg(feature = "trace")]
+use tracing_subscriber::layer::SubscriberExt as _;
+#[cfg(feature = "trace")]
+use tracing_subscriber::util::SubscriberInitExt as _;
+#[cfg(feature = "trace")]
+use tracing_subscriber::Layer;
+#[cfg(feature = "trace")]
+use tracing_subscriber::Registry;
+
+#[cfg(feature = "trace")]
+use crate::capture::CaptureLayer;
+use crate::capture::Captured;
+#[cfg(any(feature = "log", feature = "trace"))]
+use crate::capture::Level;
+use crate::capture::Query;
+use crate::check;
+use crate::check::Deny;
+#[cfg(any(feature = "log", feature = "trace"))]
+use crate::filter;
+#[cfg(feature = "trace")]
+use crate::filter::env_filter;
+use crate::filter::EnvPolicy;
+use crate::output;
+use crate::output::Buffer;
+use crate::output::Capture;
+#[cfg(feature = "chrome-trace")]
+use crate::output::ChromeGuard;
+use crate::output::Color;
+#[cfg(feature = "flame")]
+use crate::output::FlameGuard;
+use crate::output::Format;
+use crate::output::LogFile;
+#[cfg(all(feature = "log", not(feature = "trace")))]
+use crate::output::Stderr;
+#[cfg(all(feature = "log", not(feature = "trace")))]
+use crate::output::Tee;
+#[cfg(feature = "trace")]
+use crate::output::Writer;
+
+#[cfg(all(feature = "log", not(feature = "trace")))]
+use crate::format;
+#[cfg(all(feature = "log", not(feature = "trace")))]
+use crate::logger;
+
+
+thread_local! {
+  /// The stack of scopes currently entered on this thread.
+  static SCOPES: RefCell<Vec<Arc<Shared>>> = const { RefCell::new(Vec::new()) };
+}
+
+
+/// Retrieve the innermost scope entered on the current thread, if any.
+pub(crate) fn current() -> Option<Arc<Shared>> {
+  SCOPES.with(|scopes| scopes.borrow().last().cloned())
+}
+
+
+/// Per-test state that is accessible from the current thread while the
+/// test's scope is entered.
+#[derive(Debug, Default)]
+pub(crate) struct Shared {
+  /// The events captured while running the test.
+  pub(crate) captured: Captured,
+  /// The logger configured for the test.
+  #[cfg(all(feature = "log", not(feature = "trace")))]
+  pub(crate) logger: Option<Arc<env_logger::Logger>>,
+  /// The level down to which records are captured, regardless of
+  /// whether they pass the test's filter.
+  #[cfg(all(feature = "log", not(feature = "trace")))]
+  pub(crate) capture_level: Option<Level>,
+}
+
+
+/// The logging and tracing configuration of a single test.
+///
+/// A `Scope` is created for each test annotated with the `#[test]`
+/// attribute and entered for the duration of the test. While entered,
+/// all log records and tracing events emitted on the test's thread are
+/// processed according to the test's configuration and not that of any
+/// other test that may happen to be running concurrently.
+#[derive(Debug, Default)]
+pub struct Scope {
+  /// The fully qualified name of the test.
+  name: Option<String>,
+  /// The logger to use for the test.
+  #[cfg(all(feature = "log", not(feature = "trace")))]
+  logger: Option<env_logger::Logger>,
+  /// The layer emitting the output of `tracing` events.
+  #[cfg(feature = "trace")]
+  layer: Option<OutputLayer>,
+  /// Queries that have to match at least one captured event each.
+  expectations: Vec<Query>,
+  /// The configuration for denying events at or above a level.
+  deny: Option<Deny>,
+  /// The buffer recording the test's output, if it is only to be
+  /// emitted once the test failed.
+  output: Option<Buffer>,
+  /// The file the test's output is written to as well, if any.
+  file: Option<LogFile>,
+  /// The name of the environment variable to use instead of `RUST_LOG`.
+  env_var: Option<String>,
+  /// The policy for combining `RUST_LOG` with the test's filter.
+  env_policy: EnvPolicy,
+  /// Whether to color the test's output.
+  color: Color,
+  /// The format of the test's output, if not the backend's default.
+  format: Option<Format>,
+}
+
+impl Scope {
+  /// Create a new, empty `Scope`.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Set the fully qualified name of the test, as included in its
+  /// output in the JSON format.
+  pub fn name(mut self, name: Option<&str>) -> Self {
+    self.name = name.map(str::to_string);
+    self
+  }
+
+  /// Retrieve the fully qualified name of the test.
+  #[cfg(feature = "trace")]
+  pub(crate) fn test_name(&self) -> Option<&str> {
+    self.name.as_deref()
+  }
+
+  /// Set the mode determining when the test's output is emitted.
+  ///
+  /// The `RUST_LOG_CAPTURE` environment variable, if set, takes
+  /// precedence over the provided mode.
+  ///
+  /// # Panics
+  /// This method panics if the mode to use is invalid.
+  pub fn capture(mut self, mode: Option<&str>) -> Self {
+    self.output = match Capture::from_env_or(mode) {
+      Capture::Off => None,
+      Capture::OnFailure => Some(Buffer::default()),
+    };
+    self
+  }
+
+  /// Set whether to write the test's output to a log file as well, one
+  /// of `off` (the default) or `on`.
+  ///
+  /// The `RUST_LOG_FILES` environment variable, if set, takes
+  /// precedence over the provided mode. Log files are only written for
+  /// scopes that have a [name][Scope::name].
+  ///
+  /// # Panics
+  /// This method panics if the mode to use is invalid or if the log
+  /// file could not be created.
+  pub fn log_files(mut self, mode: Option<&str>) -> Self {
+    self.file = match (&self.name, output::log_files_from_env_or(mode)) {
+      (Some(name), true) => match LogFile::open(name) {
+        Ok(file) => Some(file),
+        Err(err) => panic!("test-log: failed to create log file for `{name}`: {err}"),
+      },
+      _ => None,
+    };
+    self
+  }
+
+  /// Set whether to color the test's output, one of `auto` (the
+  /// default), `always`, or `never`.
+  ///
+  /// # Panics
+  /// This method panics if `color` is not a valid choice.
+  pub fn color(mut self, color: Option<&str>) -> Self {
+    self.color = color.map(Color::parse).unwrap_or_default();
+    self
+  }
+
+  /// Set the format of the test's output, one of `full`, `compact`,
+  /// `pretty`, or `json`.
+  ///
+  /// The `RUST_LOG_FORMAT` environment variable, if set, takes
+  /// precedence over the provided format.
+  ///
+  /// # Panics
+  /// This method panics if the format to use is invalid.
+  pub fn format(mut self, format: Option<&str>) -> Self {
+    self.format = Format::from_env_or(format);
+    self
+  }
+
+  /// Retrieve the format of the test's output, if not the backend's
+  /// default.
+  #[cfg(feature = "trace")]
+  pub(crate) fn event_format(&self) -> Option<Format> {
+    self.format
+  }
+
+  /// Set the name of the environment variable providing filter
+  /// directives, falling back to `RUST_LOG` if `None`.
+  pub fn env_var(mut self, var: Option<&str>) -> Self {
+    self.env_var = var.map(str::to_string);
+    self
+  }
+
+  /// Set the policy for combining the `RUST_LOG` environment variable
+  /// with the test's filter.
+  ///
+  /// # Panics
+  /// This method panics if `policy` is not a valid policy.
+  pub fn env_policy(mut self, policy: Option<&str>) -> Self {
+    self.env_policy = policy.map(EnvPolicy::parse).unwrap_or_default();
+    self
+  }
+
+  /// Retrieve the filter directives to use, combining `default` with
+  /// the `RUST_LOG` (or configured) environment variable as per the
+  /// scope's policy.
+  ///
+  /// If the test's output is only emitted on failure, everything is
+  /// recorded, regardless of `default`.
+  #[cfg(any(feature = "log", feature = "trace"))]
+  pub fn filter(&self, default: &str) -> String {
+    let default = if self.output.is_some() {
+      "trace"
+    } else {
+      default
+    };
+    let var = self.env_var.as_deref().unwrap_or(filter::FILTER_ENV);
+    filter::combine(default, var, self.env_policy)
+  }
+
+  /// Create a builder for the `env_logger` logger to use, configured
+  /// with the filter as per [`Scope::filter`].
+  #[cfg(all(feature = "log", not(feature = "trace")))]
+  pub fn log_builder(&self, default: &str) -> env_logger::Builder {
+    let mut builder = env_logger::Builder::new();
+    builder.write_style(self.color.write_style());
+    if let Ok(style) = env::var(env_logger::DEFAULT_WRITE_STYLE_ENV) {
+      builder.parse_write_style(&style);
+    }
+    builder.parse_filters(&filter::log_directives(&self.filter(default)));
+    builder.target(match (&self.output, &self.file) {
+      (None, None) => env_logger::Target::Stderr,
+      (Some(buffer), None) => env_logger::Target::Pipe(Box::new(buffer.clone())),
+      (None, Some(file)) => env_logger::Target::Pipe(Box::new(Tee(Stderr, Some(file.clone())))),
+      (Some(buffer), Some(file)) => {
+        env_logger::Target::Pipe(Box::new(Tee(buffer.clone(), Some(file.clone()))))
+      },
+    });
+    if let Some(format) = self.format {
+      let name = self.name.clone();
+      builder.format(move |buf, record| format::write_record(format, name.as_deref(), buf, record));
+    }
+    builder.is_test(true);
+    builder
+  }
+
+  /// Check whether the `tracing` subscriber should emit ANSI escape
+  /// sequences for coloring output.
+  #[cfg(feature = "trace")]
+  pub fn ansi(&self) -> bool {
+    self.color.ansi()
+  }
+
+  /// Retrieve the writer that the `tracing` subscriber should use.
+  #[cfg(feature = "trace")]
+  pub fn make_writer(&self) -> Writer {
+    Writer(self.output.clone(), self.file.clone())
+  }
+
+  /// Set the `env_logger` logger to use for `log` records.
+  #[cfg(all(feature = "log", not(feature = "trace")))]
+  pub fn with_logger(mut self, logger: env_logger::Logger) -> Self {
+    self.logger = Some(logger);
+    self
+  }
+
+  /// Set the layer emitting the output of `tracing` events, along
+  /// with the filter directives to apply to it.
+  #[cfg(feature = "trace")]
+  pub fn with_layer<L>(mut self, layer: L, directives: Vec<Directive>) -> Self
+  where
+    L: Layer<Registry> + Send + Sync,
+  {
+    self.layer = Some(OutputLayer {
+      layer: Box::new(layer),
+      directives,
+    });
+    self
+  }
+
+  /// Determine the level down to which events have to be captured
+  /// regardless of the test's filter, for checking its expectations
+  /// and denied events.
+  #[cfg(any(feature = "log", feature = "trace"))]
+  fn capture_level(&self) -> Option<Level> {
+    self
+      .expectations
+      .iter()
+      .map(|query| query.level.unwrap_or(Level::Trace))
+      .chain(self.deny.as_ref().map(|deny| deny.level))
+      .max()
+  }
+
+  /// Create the dispatcher to use for the test.
+  ///
+  /// The output layer is combined with one recording all events that
+  /// pass the test's filter or are at or above `level`, for later
+  /// retrieval via [`captured`][crate::captured]. With the
+  /// `chrome-trace` and `flame` features enabled, spans and events
+  /// passing the filter are also written to the test's Chrome trace
+  /// event file and folded stack file, respectively.
+  ///
+  /// # Panics
+  /// This method panics if any of these files could not be created.
+  #[cfg(feature = "trace")]
+  fn dispatch(
+    name: Option<&str>,
+    layer: OutputLayer,
+    level: Option<Level>,
+  ) -> (Dispatch, TraceFiles) {
+    let OutputLayer { layer, directives } = layer;
+
+    #[cfg(feature = "chrome-trace")]
+    let (layer, chrome) = {
+      let chrome = name.map(|name| {
+        output::chrome_layer(name)
+          .unwrap_or_else(|err| panic!("test-log: failed to create trace file for `{name}`: {err}"))
+      });
+      let (chrome, guard) = chrome.unzip();
+      (layer.and_then(chrome), guard)
+    };
+
+    #[cfg(feature = "flame")]
+    let (layer, flame) = {
+      let flame = name.map(|name| {
+        output::flame_layer(name)
+          .unwrap_or_else(|err| panic!("test-log: failed to create flame file for `{name}`: {err}"))
+      });
+      let (flame, guard) = flame.unzip();
+      (layer.and_then(flame), guard)
+    };
+
+    let filter = env_filter(&directives);
+    let layer: Box<dyn Layer<Registry> + Send + Sync> = match level {
+      // With nothing to capture beyond what passes the filter, it can
+      // just apply globally, keeping filtered out events cheap.
+      None => Box::new(layer.and_then(CaptureLayer).and_then(filter)),
+      Some(level) => {
+        let level = LevelFilter::from_level(level.into());
+        let capture = env_filter(&directives).or(level);
+        Box::new(
+          layer
+            .with_filter(filter)
+            .and_then(CaptureLayer.with_filter(capture)),
+        )
+      },
+    };
+    let subscriber = Registry::default().with(layer);
+
+    #[cfg(not(any(feature = "chrome-trace", feature = "flame")))]
+    let _name = name;
+
+    let files = TraceFiles {
+      #[cfg(feature = "chrome-trace")]
+      _chrome: chrome,
+      #[cfg(feature = "flame")]
+      _flame: flame,
+    };
+    (Dispatch::new(subscriber), files)
+  }
+
+  /// Require that at least one event matching `query` is emitted by the
+  /// test.
+  pub fn expect_log(mut self, query: Query) -> Self {
+    self.expectations.push(query);
+    self
+  }
+
+  /// Fail the test if it emits any event at or above `level`, unless
+  /// the event's target is part of the comma separated list of
+  /// `exempt` targets.
+  ///
+  /// # Panics
+  /// This method panics if `level` is not a valid level.
+  pub fn deny_level(mut self, level: Option<&str>, exempt: Option<&str>) -> Self {
+    self.deny = level.map(|level| Deny::parse(level, exempt));
+    self
+  }
+
+  /// Start the scope, activating its configuration.
+  ///
+  /// The scope is not yet entered on any thread; use
+  /// [`ScopeGuard::enter`] or [`ScopeGuard::scoped`] for that.
+  pub fn start(self) -> ScopeGuard {
+    #[cfg(all(feature = "log", not(feature = "trace")))]
+    let shared = {
+      let capture_level = self.capture_level();
+      let level = capture_level
+        .map(|level| logging::Level::from(level).to_level_filter())
+        .max(self.logger.as_ref().map(env_logger::Logger::filter))
+        .unwrap_or(logging::LevelFilter::Off);
+      let () = logger::install(None, level);
+      Shared {
+        capture_level,
+        logger: self.logger.map(Arc::new),
+        ..Default::default()
+      }
+    };
+    #[cfg(not(all(feature = "log", not(feature = "trace"))))]
+    let shared = Shared::default();
+
+    #[cfg(feature = "trace")]
+    let level = self.capture_level();
+    #[cfg(feature = "trace")]
+    let (dispatch, files) = match self.layer {
+      Some(layer) => {
+        let (dispatch, files) = Self::dispatch(self.name.as_deref(), layer, level);
+        // Setting a default, even if only briefly, also takes care of
+        // forwarding `log` records to the current dispatcher, if
+        // enabled.
+        let _guard = dispatch.clone().set_default();
+        (Some(dispatch), files)
+      },
+      None => (None, TraceFiles::default()),
+    };
+
+    ScopeGuard {
+      shared: Arc::new(shared),
+      expectations: self.expectations,
+      deny: self.deny,
+      output: self.output,
+      #[cfg(feature = "trace")]
+      dispatch,
+      #[cfg(feature = "trace")]
+      _files: files,
+    }
+  }
+
+  /// Install the scope's configuration as the one used for all threads
+  /// not associated with any test, if no configuration was installed
+  /// before.
+  ///
+  /// Contrary to [`Scope::start`], events are not captured.
+  pub fn install(self) {
+    #[cfg(all(feature = "log", not(feature = "trace")))]
+    if let Some(logger) = self.logger {
+      let level = logger.filter();
+      let () = logger::install(Some(logger), level);
+    }
+
+    #[cfg(feature = "trace")]
+    if let Some(OutputLayer { layer, directives }) = self.layer {
+      // Without any other layers around, the filter can just apply
+      // globally.
+      let dispatch = Dispatch::new(
+        Registry::default()
+          .with(layer)
+          .with(env_filter(&directives)),
+      );
+      let _result = dispatcher::set_global_default(dispatch.clone());
+      let _guard = dispatch.set_default();
+    }
+  }
+}
+
+
+/// A layer emitting the output of `tracing` events, along with its
+/// filter.
+#[cfg(feature = "trace")]
+struct OutputLayer {
+  /// The layer emitting output.
+  layer: Box<dyn Layer<Registry> + Send + Sync>,
+  /// The filter directives determining which events are emitted.
+  directives: Vec<Directive>,
+}
+
+#[cfg(feature = "trace")]
+impl Debug for OutputLayer {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    f.debug_struct("OutputLayer")
+      .field("directives", &self.directives)
+      .finish_non_exhaustive()
+  }
+}
+
+
+/// The guards for the files written by a test's dispatcher.
+#[cfg(feature = "trace")]
+#[derive(Debug, Default)]
+struct TraceFiles {
+  /// The guard completing the test's Chrome trace event file.
+  #[cfg(feature = "chrome-trace")]
+  _chrome: Option<ChromeGuard>,
+  /// The guard flushing the test's folded stack file.
+  #[cfg(feature = "flame")]
+  _flame: Option<FlameGuard>,
+}
+
+
+/// A guard representing a started `Scope`, which can be entered on
+/// threads and futures and is finished once the test is done.
+#[derive(Debug)]
+#[must_use = "the scope has to be entered to take effect"]
+pub struct ScopeGuard {
+  /// The state shared with the scope.
+  shared: Arc<Shared>,
+  /// Queries that have to match at least one captured event each.
+  expectations: Vec<Query>,
+  /// The configuration for denying events at or above a level.
+  deny: Option<Deny>,
+  /// The buffer recording the test's output, if any.
+  output: Option<Buffer>,
+  /// The dispatcher to use for the test.
+  #[cfg(feature = "trace")]
+  dispatch: Option<Dispatch>,
+  /// The files written by the test's dispatcher, completed once
+  /// dropped.
+  #[cfg(feature = "trace")]
+  _files: TraceFiles,
+}
+
+impl ScopeGuard {
+  /// Enter the scope on the current thread, making it the active one
+  /// until the returned guard is dropped.
+  pub fn enter(&self) -> Entered {
+    #[cfg(feature = "trace")]
+    let dispatch = self.dispatch.as_ref().map(dispatcher::set_default);
+
+    Entered {
+      _scope: Pushed::new(&self.shared),
+      #[cfg(feature = "trace")]
+      _dispatch: dispatch,
+    }
+  }
+
+  /// Enter the scope every time the provided future is polled.
+  ///
+  /// Contrary to entering the scope on the current thread, the scope
+  /// follows the future across `.await` points and onto whatever
+  /// thread ends up polling it.
+  pub fn scoped<F>(&self, future: F) -> Scoped<F>
+  where
+    F: Future,
+  {
+    Scoped {
+      shared: Arc::clone(&self.shared),
+      #[cfg(feature = "trace")]
+      dispatch: self.dispatch.clone(),
+      future: Box::pin(future),
+    }
+  }
+
+  /// Finish the scope after the test body has run to completion,
+  /// checking that all expectations on emitted events are met and that
+  /// no denied events were emitted.
+  ///
+  /// `failed` indicates whether the test failed by returning an error,
+  /// in which case the checks are skipped and any recorded output is
+  /// emitted.
+  ///
+  /// # Panics
+  /// This method panics if any of the checks fails.
+  pub fn finish(mut self, failed: bool) {
+    if failed {
+      if let Some(output) = self.output.take() {
+        output.dump();
+      }
+      return
+    }
+
+    let captured = &self.shared.captured;
+    check::expectations(captured, &self.expectations);
+    if let Some(deny) = &self.deny {
+      check::deny(captured, deny);
+    }
+  }
+}
+
+impl Drop for ScopeGuard {
+  fn drop(&mut self) {
+    // A panic, be it in the test body or in one of our checks, means
+    // that the test failed.
+    if thread::panicking() {
+      if let Some(output) = self.output.take() {
+        output.dump();
+      }
+    }
+  }
+}
+
+
+/// A helper making a scope's shared state the current one on the
+/// current thread, for as long as it is alive.
+#[derive(Debug)]
+struct Pushed(());
+
+impl Pushed {
+  fn new(shared: &Arc<Shared>) -> Self {
+    SCOPES.with(|scopes| scopes.borrow_mut().push(Arc::clone(shared)));
+    Self(())
+  }
+}
+
+impl Drop for Pushed {
+  fn drop(&mut self) {
+    let _shared = SCOPES.with(|scopes| scopes.borrow_mut().pop());
+  }
+}
+
+
+/// A guard keeping a scope entered on the current thread; the scope is
+/// exited when the guard is dropped.
+#[derive(Debug)]
+#[must_use = "the scope is exited as soon as the guard is dropped"]
+pub struct Entered {
+  /// The scope's shared state, made current.
+  _scope: Pushed,
+  /// The guard for the scoped `tracing` dispatcher.
+  #[cfg(feature = "trace")]
+  _dispatch: Option<DefaultGuard>,
+}
+
+
+/// A future entering a scope every time it is polled.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct Scoped<F> {
+  /// The state shared with the scope.
+  shared: Arc<Shared>,
+  /// The dispatcher to use for the test.
+  #[cfg(feature = "trace")]
+  dispatch: Option<Dispatch>,
+  /// The wrapped future.
+  future: Pin<Box<F>>,
+}
+
+impl<F> Future for Scoped<F>
+where
+  F: Future,
+{
+  type Output = F::Output;
+
+  fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    let this = &mut *self;
+    let _scope = Pushed::new(&this.shared);
+
+    #[cfg(feature = "trace")]
+    if let Some(dispatch) = &this.dispatch {
+      return dispatcher::with_default(dispatch, || this.future.as_mut().poll(cx))
+    }
+
+    this.future.as_mut().poll(cx)
+  }
+}
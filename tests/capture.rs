@@ -0,0 +1,118 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: (Apache-2.0 OR MIT)
+
+//! Tests for the in-memory capture of log records and tracing events.
+
+#![cfg(any(feature = "log", feature = "trace"))]
+
+use test_log::Level;
+use test_log::Query;
+
+
+/// Check that `log` records are captured and can be queried.
+#[cfg(feature = "log")]
+#[test_log::test]
+fn capture_log_records() {
+  logging::warn!(target: "my_crate::retry", "request failed; retrying");
+  logging::error!("giving up");
+
+  let captured = test_log::captured();
+  let events = captured.find(&Query::new().level(Level::Warn));
+  assert_eq!(events.len(), 1);
+  assert_eq!(events[0].target(), "my_crate::retry");
+  assert_eq!(events[0].message(), "request failed; retrying");
+  assert_eq!(events[0].module_path(), Some(module_path!()));
+
+  assert!(captured.contains(&Query::new().target("my_crate").contains("retrying")));
+  assert!(!captured.contains(&Query::new().target("my_cr").contains("retrying")));
+  assert!(captured.contains(&Query::new().level(Level::Error).contains("giving up")));
+  assert!(!captured.contains(&Query::new().level(Level::Warn).contains("giving up")));
+}
+
+/// Make sure that key-value pairs of `log` records are captured as
+/// fields.
+#[cfg(all(feature = "log", not(feature = "trace")))]
+#[test_log::test]
+fn capture_log_key_values() {
+  logging::warn!(attempt = 2; "retrying");
+
+  let captured = test_log::captured();
+  assert!(captured.contains(&Query::new().contains("retrying").field("attempt", "2")));
+  assert!(!captured.contains(&Query::new().contains("retrying").field("attempt", "3")));
+}
+
+/// Check that `tracing` events and their fields are captured.
+#[cfg(feature = "trace")]
+#[test_log::test]
+fn capture_tracing_events() {
+  tracing::warn!(attempt = 2, host = "localhost", "retrying");
+
+  let captured = test_log::captured();
+  let events = captured.find(&Query::new().level(Level::Warn).contains("retrying"));
+  assert_eq!(events.len(), 1);
+  assert_eq!(events[0].target(), module_path!());
+  assert_eq!(events[0].message(), "retrying");
+  assert_eq!(events[0].field("attempt"), Some("2"));
+  assert_eq!(events[0].field("host"), Some("localhost"));
+  assert_eq!(events[0].field("message"), None);
+}
+
+/// Verify that events of other, concurrently running tests are not
+/// captured.
+#[test_log::test]
+fn capture_is_per_test() {
+  let captured = test_log::captured();
+  assert_eq!(captured.events(), Vec::new());
+}
+
+/// Test that captured events can be discarded.
+#[test_log::test]
+fn capture_clear() {
+  #[cfg(feature = "log")]
+  logging::error!("first");
+  #[cfg(feature = "trace")]
+  tracing::error!("first");
+
+  let captured = test_log::captured();
+  assert!(!captured.events().is_empty());
+
+  captured.clear();
+  assert_eq!(captured.events(), Vec::new());
+}
+
+/// Make sure that a scope entered by a future follows it onto the thread
+/// polling it.
+#[cfg(feature = "trace")]
+#[test]
+fn capture_follows_future_across_threads() {
+  use std::thread;
+
+  use test_log::__private::init;
+  use test_log::__private::Config;
+  use tokio::runtime::Builder;
+
+  let guard = init(&Config::DEFAULT);
+  let future = guard.scoped(async {
+    tracing::warn!("polled elsewhere");
+    test_log::captured()
+  });
+
+  let captured = thread::spawn(move || {
+    Builder::new_current_thread()
+      .build()
+      .unwrap()
+      .block_on(future)
+  })
+  .join()
+  .unwrap();
+  assert!(captured.contains(&Query::new().level(Level::Warn).contains("polled elsewhere")));
+  guard.finish(false);
+}
+
+/// Check that retrieving captured events outside of a test annotated
+/// with `#[test_log::test]` panics.
+#[test]
+#[should_panic(expected = "must be called from within a #[test_log::test]")]
+fn capture_outside_of_test() {
+  let _captured = test_log::captured();
+}
@@ -19,3 +19,67 @@ async fn with_inner_test_attribute_and_default_log_filter_defined() {
   assert!(env::var(env_logger::DEFAULT_FILTER_ENV).is_err());
   assert!(log_enabled!(Level::Debug));
 }
+
+/// Check that a test's `default_log_filter` is honored even if another
+/// test with a different filter is running concurrently.
+#[test_log::test]
+#[test_log(default_log_filter = "debug")]
+fn default_log_filter_is_per_test_debug() {
+  if env::var_os(env_logger::DEFAULT_FILTER_ENV).is_none() {
+    assert!(log_enabled!(Level::Debug));
+  }
+}
+
+/// Counterpart to `default_log_filter_is_per_test_debug`, using a more
+/// restrictive filter.
+#[test_log::test]
+#[test_log(default_log_filter = "warn")]
+fn default_log_filter_is_per_test_warn() {
+  if env::var_os(env_logger::DEFAULT_FILTER_ENV).is_none() {
+    assert!(log_enabled!(Level::Warn));
+    assert!(!log_enabled!(Level::Info));
+  }
+}
+
+/// Check that the default filter can be specified in the main attribute,
+/// without requiring the `unstable` feature.
+#[test_log::test(filter = "debug")]
+fn default_log_filter_in_main_attribute() {
+  if env::var_os(env_logger::DEFAULT_FILTER_ENV).is_none() {
+    assert!(log_enabled!(Level::Debug));
+  }
+}
+
+/// Verify that inline configuration can be combined with an inner test
+/// attribute.
+#[test_log::test(tokio::test, filter = "warn")]
+async fn default_log_filter_in_main_attribute_with_inner_test() {
+  if env::var_os(env_logger::DEFAULT_FILTER_ENV).is_none() {
+    assert!(log_enabled!(Level::Warn));
+    assert!(!log_enabled!(Level::Info));
+  }
+}
+
+/// Check that default filters consisting of multiple directives are
+/// honored by all backends.
+#[test_log::test(filter = "default_log_filter=debug,hyper=warn,info")]
+fn default_log_filter_with_multiple_directives() {
+  if env::var_os(env_logger::DEFAULT_FILTER_ENV).is_none() {
+    assert!(log_enabled!(Level::Debug));
+    assert!(!log_enabled!(target: "hyper", Level::Info));
+    assert!(log_enabled!(target: "hyper", Level::Warn));
+    assert!(log_enabled!(target: "other", Level::Info));
+    assert!(!log_enabled!(target: "other", Level::Debug));
+  }
+}
+
+/// Check that the structured filter form is honored by all backends.
+#[test_log::test(filter(default = "info", hyper = "warn", "default_log_filter" = "debug"))]
+fn default_log_filter_structured() {
+  if env::var_os(env_logger::DEFAULT_FILTER_ENV).is_none() {
+    assert!(log_enabled!(Level::Debug));
+    assert!(!log_enabled!(target: "hyper", Level::Info));
+    assert!(log_enabled!(target: "other", Level::Info));
+    assert!(!log_enabled!(target: "other", Level::Debug));
+  }
+}
@@ -0,0 +1,30 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: (Apache-2.0 OR MIT)
+
+//! Tests for runtime initialization via `test_log::Builder`, in a test
+//! without the default harness, as the attribute cannot be used there.
+
+use std::env;
+
+
+fn main() {
+  test_log::Builder::new().filter("info,init=debug").init();
+  // Subsequent initialization has no effect.
+  test_log::Builder::new().filter("off").init();
+
+  if env::var_os("RUST_LOG").is_none() {
+    #[cfg(feature = "log")]
+    {
+      assert!(logging::log_enabled!(target: "init", logging::Level::Debug));
+      assert!(!logging::log_enabled!(target: "init", logging::Level::Trace));
+      assert!(!logging::log_enabled!(target: "other", logging::Level::Debug));
+    }
+
+    #[cfg(feature = "trace")]
+    {
+      assert!(tracing::enabled!(target: "init", tracing::Level::DEBUG));
+      assert!(!tracing::enabled!(target: "init", tracing::Level::TRACE));
+      assert!(!tracing::enabled!(target: "other", tracing::Level::DEBUG));
+    }
+  }
+}
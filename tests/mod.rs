@@ -3,6 +3,9 @@
 
 //! Tests for test-log.
 
+#[cfg(feature = "trace")]
+use std::env;
+
 use rstest::rstest;
 
 use tokio::runtime::Builder;
@@ -11,6 +14,8 @@ use tracing::debug;
 use tracing::error;
 use tracing::info;
 use tracing::instrument;
+#[cfg(all(feature = "unstable", feature = "trace"))]
+use tracing::warn;
 
 
 mod something {
@@ -19,6 +24,10 @@ mod something {
 
 use something::Error;
 
+mod facade {
+  pub use ::test_log;
+}
+
 
 #[test_log::test]
 fn without_return_type() {
@@ -30,6 +39,15 @@ fn with_return_type() -> Result<(), Error> {
   Ok(())
 }
 
+#[test_log::test]
+fn with_early_return_and_try_operator() -> Result<(), Error> {
+  let value = "42".parse::<u8>().map_err(|err| err.to_string())?;
+  if value == 42 {
+    return Ok(())
+  }
+  Err("unexpected value".to_string())
+}
+
 #[test_log::test]
 #[should_panic(expected = "success")]
 fn with_panic() {
@@ -63,6 +81,16 @@ fn with_inner_test_attribute_and_test_args_and_panic(x: i8, _y: i8) {
 #[test]
 fn with_existing_test_attribute() {}
 
+#[test_log::test(crate = "crate::facade::test_log")]
+fn with_crate_path() {
+  info!("referring to test-log via a facade");
+}
+
+#[test_log::test(tokio::test, crate = "crate::facade::test_log")]
+async fn with_crate_path_and_async() {
+  assert_eq!(async { 42 }.await, 42)
+}
+
 #[test_log::test]
 #[::core::prelude::v1::test]
 fn with_existing_generated_test_attribute() {}
@@ -153,6 +181,84 @@ async fn trace_with_default_log_filter() {
   debug!("done");
 }
 
+#[cfg(all(feature = "unstable", feature = "trace"))]
+#[test_log::test]
+#[test_log(expect_log(level = "warn", contains = "retrying"))]
+#[test_log(expect_log(level = "error", contains = "giving up"))]
+fn with_expect_log() {
+  warn!("request failed; retrying");
+  error!("giving up");
+}
+
+#[cfg(all(feature = "unstable", feature = "trace"))]
+#[test_log::test]
+#[test_log(expect_log(level = "warn", contains = "retrying"))]
+#[should_panic(expected = "expected an event matching `level = warn, contains = \"retrying\"`")]
+fn with_expect_log_unmet() {
+  info!("request failed; retrying");
+}
+
+/// Check that an expectation can be met by an event that does not pass
+/// the test's filter.
+#[cfg(all(feature = "unstable", feature = "trace"))]
+#[test_log::test(expect_log(level = "debug", contains = "hidden"))]
+fn with_expect_log_below_filter() {
+  debug!("hidden from the output");
+}
+
+/// Check that events are only enabled as far as needed for checking
+/// denied events, in addition to the test's filter.
+#[cfg(feature = "trace")]
+#[test_log::test(deny_level = "warn")]
+fn with_deny_level_enabled() {
+  if env::var_os("RUST_LOG").is_none() {
+    assert!(tracing::enabled!(tracing::Level::INFO));
+    assert!(!tracing::enabled!(tracing::Level::DEBUG));
+  }
+}
+
+#[cfg(all(feature = "unstable", feature = "trace"))]
+#[test_log::test(tokio::test)]
+#[test_log(expect_log(level = "error", contains = "here we go"))]
+async fn with_expect_log_and_async() {
+  instrumented(4).await;
+}
+
+#[cfg(all(feature = "unstable", feature = "trace"))]
+#[test_log::test]
+#[test_log(deny_level = "error")]
+fn with_deny_level() {
+  warn!("request failed; retrying");
+}
+
+#[cfg(all(feature = "unstable", feature = "trace"))]
+#[test_log::test]
+#[test_log(deny_level = "warn")]
+#[should_panic(expected = "1 event(s) at or above level `warn` were emitted")]
+fn with_deny_level_violated() {
+  info!("request failed; retrying");
+  warn!("giving up");
+}
+
+#[cfg(all(feature = "unstable", feature = "trace"))]
+#[test_log::test]
+#[test_log(deny_level = "error")]
+#[test_log(deny_exempt = "noisy::dependency, other")]
+fn with_deny_level_and_exempt_targets() {
+  error!(target: "noisy::dependency::client", "connection reset");
+  error!(target: "other", "connection reset");
+}
+
+#[cfg(all(feature = "unstable", feature = "trace"))]
+#[test_log::test]
+#[test_log(
+  deny_level = "error",
+  expect_log(level = "warn", contains = "retrying")
+)]
+fn with_multiple_args_in_single_attribute() {
+  warn!("request failed; retrying");
+}
+
 #[test_log::test(tokio::test(flavor = "multi_thread", worker_threads = 1))]
 async fn trace_with_tokio_attribute_with_arguments() {
   instrumented(6).await;
@@ -218,3 +324,90 @@ mod local {
     assert_eq!(x, 0);
   }
 }
+
+/// Check that the body of a test is wrapped in a span named after the
+/// test.
+#[cfg(feature = "trace")]
+#[test_log::test]
+fn trace_span_named_after_test() {
+  if let Some(metadata) = tracing::Span::current().metadata() {
+    assert_eq!(
+      metadata.name(),
+      concat!(module_path!(), "::trace_span_named_after_test")
+    );
+  }
+}
+
+/// Check that the body of an `async` test is wrapped in a span named
+/// after the test.
+#[cfg(feature = "trace")]
+#[tokio::test]
+#[test_log::test]
+async fn trace_span_named_after_async_test() {
+  async {}.await;
+  if let Some(metadata) = tracing::Span::current().metadata() {
+    assert_eq!(
+      metadata.name(),
+      concat!(module_path!(), "::trace_span_named_after_async_test")
+    );
+  }
+}
+
+/// Check that span field directives in the default filter are honored.
+#[cfg(feature = "trace")]
+#[test_log::test(filter = "info,[request{id=5,user}]=trace")]
+fn trace_with_span_field_directive() {
+  if env::var_os("RUST_LOG").is_none() {
+    assert!(!tracing::enabled!(tracing::Level::TRACE));
+
+    let span = tracing::info_span!("request", id = 5, user = "alice");
+    let _entered = span.enter();
+    assert!(tracing::enabled!(tracing::Level::TRACE));
+  }
+}
+
+/// Tests converted by `test_module`, inheriting its configuration.
+#[cfg(feature = "trace")]
+#[test_log::test_module(expect_log(contains = "from module"))]
+mod module {
+  use rstest::rstest;
+
+  use tracing::info;
+
+
+  #[test]
+  fn with_test_attribute() {
+    info!("from module");
+  }
+
+  #[test]
+  #[should_panic(expected = "expected an event matching `contains = \"from module\"`")]
+  fn with_inherited_expectation_unmet() {}
+
+  #[tokio::test]
+  async fn with_tokio_test_attribute() {
+    info!("from module");
+  }
+
+  #[rstest]
+  #[case(-2, -4)]
+  #[case(-3, -4)]
+  fn with_rstest_attribute(#[case] x: i8, #[case] _y: i8) {
+    assert!(x < 0);
+    info!("from module");
+  }
+
+  #[test_case::test_case(-2, -4)]
+  fn with_test_case_attribute(x: i8, _y: i8) {
+    assert!(x < 0);
+    info!("from module");
+  }
+
+  #[test_log::test(expect_log(contains = "from test"))]
+  fn with_own_configuration() {
+    info!("from test");
+  }
+
+  #[allow(dead_code)]
+  fn not_a_test() {}
+}
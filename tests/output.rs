@@ -9,8 +9,11 @@
 #![cfg(any(feature = "log", feature = "trace"))]
 
 use std::env;
+use std::fs;
+use std::path::PathBuf;
 use std::process::Command;
 use std::process::Output;
+use std::thread;
 
 
 #[cfg(feature = "log")]
@@ -34,22 +37,71 @@ const TRACE_WARN: &str = "MARKER_TRACE_WARN_jkl012";
 const TRACE_INFO_LEVELS: &str = "MARKER_TRACE_INFO_mno345";
 #[cfg(feature = "trace")]
 const TRACE_DEBUG: &str = "MARKER_TRACE_DEBUG_pqr678";
+#[cfg(feature = "trace")]
+const TRACE_SPAN: &str = "MARKER_TRACE_SPAN_hij456";
+#[cfg(feature = "trace")]
+const TRACE_OUTSIDE: &str = "MARKER_TRACE_OUTSIDE_cde345";
+#[cfg(feature = "trace")]
+const TRACE_INSIDE: &str = "MARKER_TRACE_INSIDE_fgh678";
 
+/// A marker emitted at the `info` level by tests combining `RUST_LOG`
+/// with their filter.
+const ENV_INFO: &str = "MARKER_ENV_INFO_klm789";
+/// A marker emitted at the `debug` level for the `special` target by
+/// tests combining `RUST_LOG` with their filter.
+const ENV_DEBUG: &str = "MARKER_ENV_DEBUG_nop012";
 
-/// Run the ignored test with name `name` and capture its stderr output.
-fn run_target(name: &str, extra_env: &[(&str, &str)]) -> String {
+/// A marker emitted by tests with a non-default output format.
+const FORMAT_INFO: &str = "MARKER_FORMAT_INFO_qrs345";
+
+/// A marker emitted by tests writing their output to a log file.
+const FILE_INFO: &str = "MARKER_FILE_INFO_tuv678";
+/// A marker emitted from a thread spawned by tests writing their output
+/// to a log file.
+const FILE_THREAD: &str = "MARKER_FILE_THREAD_klm901";
+
+/// A marker emitted by tests whose spans are written to a Chrome trace
+/// event file.
+#[cfg(feature = "chrome-trace")]
+const CHROME_INFO: &str = "MARKER_CHROME_INFO_wxy901";
+
+/// A marker emitted by tests whose span timings are written to a folded
+/// stack file.
+#[cfg(feature = "flame")]
+const FLAME_INFO: &str = "MARKER_FLAME_INFO_zab234";
+
+/// A marker emitted at the `trace` level by tests whose output is only
+/// shown on failure.
+const CAPTURE_TRACE: &str = "MARKER_CAPTURE_TRACE_efg123";
+
+/// A marker emitted at the `error` level by tests denying such events.
+const DENY_ERROR: &str = "MARKER_DENY_ERROR_hij456";
+
+
+/// Run the ignored test with name `name`, returning whether it succeeded
+/// along with its stderr output.
+///
+/// An empty value in `extra_env` removes the variable from the
+/// environment.
+fn run_target_status(name: &str, extra_env: &[(&str, &str)]) -> (bool, String) {
   let exe = env::current_exe().expect("failed to determine test binary path");
   let mut cmd = Command::new(exe);
   cmd
     .args(["--ignored", "--exact", name, "--nocapture"])
     .env("NO_COLOR", "1")
-    .env_remove("RUST_LOG_SPAN_EVENTS");
+    .env_remove("RUST_LOG_SPAN_EVENTS")
+    .env_remove("RUST_LOG_FORMAT")
+    .env_remove("RUST_LOG_FILES");
 
   if !extra_env.iter().any(|(k, _)| *k == "RUST_LOG") {
     cmd.env("RUST_LOG", "info");
   }
   for (k, v) in extra_env {
-    cmd.env(k, v);
+    if v.is_empty() {
+      cmd.env_remove(k);
+    } else {
+      cmd.env(k, v);
+    }
   }
 
   let Output {
@@ -59,10 +111,50 @@ fn run_target(name: &str, extra_env: &[(&str, &str)]) -> String {
   } = cmd.output().expect("failed to execute test binary");
 
   let stderr = String::from_utf8(stderr).expect("stderr is not UTF-8");
-  assert!(status.success(), "subprocess `{name}` failed:\n{stderr}",);
+  (status.success(), stderr)
+}
+
+/// Run the ignored test with name `name` and capture its stderr output.
+fn run_target(name: &str, extra_env: &[(&str, &str)]) -> String {
+  let (success, stderr) = run_target_status(name, extra_env);
+  assert!(success, "subprocess `{name}` failed:\n{stderr}",);
   stderr
 }
 
+/// Run the ignored test with name `name` with output captured until it
+/// fails, letting it end with the given `outcome`.
+fn run_capture_target(name: &str, outcome: &str) -> (bool, String) {
+  run_target_status(
+    name,
+    &[
+      ("RUST_LOG", ""),
+      ("RUST_LOG_CAPTURE", "on-failure"),
+      ("TEST_LOG_OUTCOME", outcome),
+    ],
+  )
+}
+
+/// Retrieve the path to the file `file` in directory `dir` of the
+/// target directory, for a test in this test binary's root module.
+fn test_file(dir: &str, file: &str) -> PathBuf {
+  let exe = env::current_exe().expect("failed to determine test binary path");
+  let target = exe
+    .ancestors()
+    .find(|dir| dir.join("CACHEDIR.TAG").is_file())
+    .expect("failed to find target directory");
+  target.join(dir).join("output").join(file)
+}
+
+/// End a test according to the `TEST_LOG_OUTCOME` environment
+/// variable.
+fn outcome() -> Result<(), String> {
+  match env::var("TEST_LOG_OUTCOME").as_deref() {
+    Ok("panic") => panic!("test failed"),
+    Ok("err") => Err("test failed".to_string()),
+    _ => Ok(()),
+  }
+}
+
 
 #[cfg(feature = "log")]
 #[ignore = "target for output verification"]
@@ -98,6 +190,189 @@ fn emit_trace_levels() {
   tracing::debug!("{TRACE_DEBUG}");
 }
 
+#[cfg(feature = "trace")]
+#[ignore = "target for output verification"]
+#[test_log::test(filter = "info,[request{id=5,user}]=trace")]
+fn emit_trace_span_field_directive() {
+  tracing::trace!("{TRACE_OUTSIDE}");
+  let _span = tracing::info_span!("request", id = 5, user = "alice").entered();
+  tracing::trace!("{TRACE_INSIDE}");
+}
+
+#[cfg(all(feature = "log", not(feature = "trace")))]
+#[ignore = "target for output verification"]
+#[test_log::test(filter = "info,[request{id=5,user}]=trace")]
+fn emit_log_span_field_directive() {
+  logging::info!("{LOG_INFO}");
+  logging::debug!("{LOG_DEBUG}");
+}
+
+#[cfg(feature = "log")]
+#[ignore = "target for output verification"]
+#[test_log::test(format = "json")]
+fn emit_log_format() {
+  logging::info!("{FORMAT_INFO}");
+}
+
+#[cfg(feature = "trace")]
+#[ignore = "target for output verification"]
+#[test_log::test(format = "json")]
+fn emit_trace_format() {
+  let _span = tracing::info_span!("outer", id = 7).entered();
+  tracing::info!(answer = 42, "{FORMAT_INFO}");
+}
+
+#[cfg(feature = "log")]
+#[ignore = "target for output verification"]
+#[test_log::test(log_files = "on", color = "always")]
+fn emit_log_file() {
+  logging::info!("{FILE_INFO}");
+}
+
+#[cfg(feature = "trace")]
+#[ignore = "target for output verification"]
+#[test_log::test(log_files = "on", color = "always")]
+fn emit_trace_file() {
+  tracing::info!("{FILE_INFO}");
+}
+
+#[cfg(feature = "log")]
+#[ignore = "target for output verification"]
+#[test_log::test(log_files = "on")]
+fn emit_log_file_thread() {
+  logging::info!("{FILE_INFO}");
+  let () = thread::spawn(|| logging::info!("{FILE_THREAD}"))
+    .join()
+    .unwrap();
+}
+
+#[cfg(feature = "trace")]
+#[ignore = "target for output verification"]
+#[test_log::test(log_files = "on")]
+fn emit_trace_file_thread() {
+  tracing::info!("{FILE_INFO}");
+  let () = thread::spawn(|| tracing::info!("{FILE_THREAD}"))
+    .join()
+    .unwrap();
+}
+
+#[cfg(feature = "chrome-trace")]
+#[ignore = "target for output verification"]
+#[test_log::test]
+fn emit_trace_chrome() {
+  #[tracing::instrument]
+  fn instrumented(id: u32) {
+    tracing::info!("{CHROME_INFO}");
+  }
+
+  instrumented(7);
+}
+
+#[cfg(feature = "flame")]
+#[ignore = "target for output verification"]
+#[test_log::test]
+fn emit_trace_flame() {
+  #[tracing::instrument]
+  fn instrumented() {
+    tracing::info!("{FLAME_INFO}");
+  }
+
+  instrumented();
+  instrumented();
+}
+
+#[cfg(feature = "log")]
+#[ignore = "target for output verification"]
+#[test_log::test]
+fn emit_log_captured() -> Result<(), String> {
+  logging::trace!("{CAPTURE_TRACE}");
+  outcome()
+}
+
+#[cfg(feature = "log")]
+#[ignore = "target for output verification"]
+#[test_log::test(filter = "info", env = "merge")]
+fn emit_log_env_merge() {
+  logging::info!("{ENV_INFO}");
+  logging::debug!(target: "special", "{ENV_DEBUG}");
+}
+
+#[cfg(feature = "log")]
+#[ignore = "target for output verification"]
+#[test_log::test(filter = "info", env = "ignore")]
+fn emit_log_env_ignore() {
+  logging::info!("{ENV_INFO}");
+  logging::debug!(target: "special", "{ENV_DEBUG}");
+}
+
+#[cfg(feature = "trace")]
+#[ignore = "target for output verification"]
+#[test_log::test(filter = "info", env = "merge")]
+fn emit_trace_env_merge() {
+  tracing::info!("{ENV_INFO}");
+  tracing::debug!(target: "special", "{ENV_DEBUG}");
+}
+
+#[cfg(feature = "trace")]
+#[ignore = "target for output verification"]
+#[test_log::test(filter = "info", env = "ignore")]
+fn emit_trace_env_ignore() {
+  tracing::info!("{ENV_INFO}");
+  tracing::debug!(target: "special", "{ENV_DEBUG}");
+}
+
+#[cfg(feature = "log")]
+#[ignore = "target for output verification"]
+#[test_log::test(filter = "info", env = "OUTPUT_TEST_LOG")]
+fn emit_log_env_var() {
+  logging::info!("{ENV_INFO}");
+  logging::debug!(target: "special", "{ENV_DEBUG}");
+}
+
+#[cfg(feature = "trace")]
+#[ignore = "target for output verification"]
+#[test_log::test(filter = "info", env = "OUTPUT_TEST_LOG")]
+fn emit_trace_env_var() {
+  tracing::info!("{ENV_INFO}");
+  tracing::debug!(target: "special", "{ENV_DEBUG}");
+}
+
+#[cfg(feature = "trace")]
+#[ignore = "target for output verification"]
+#[test_log::test(span_events = "close", span_events_env = "OUTPUT_SPAN_EVENTS")]
+fn emit_trace_span_events_env_var() {
+  let _span = tracing::info_span!(TRACE_SPAN).entered();
+}
+
+#[cfg(feature = "trace")]
+#[ignore = "target for output verification"]
+#[test_log::test(span_events = "close")]
+fn emit_trace_span_events() {
+  let _span = tracing::info_span!(TRACE_SPAN).entered();
+}
+
+#[cfg(feature = "trace")]
+#[ignore = "target for output verification"]
+#[test_log::test]
+fn emit_trace_captured() -> Result<(), String> {
+  tracing::trace!("{CAPTURE_TRACE}");
+  outcome()
+}
+
+#[cfg(feature = "log")]
+#[ignore = "target for output verification"]
+#[test_log::test(deny_level = "error")]
+fn emit_log_denied() {
+  logging::error!("{DENY_ERROR}");
+}
+
+#[cfg(feature = "trace")]
+#[ignore = "target for output verification"]
+#[test_log::test(deny_level = "error")]
+fn emit_trace_denied() {
+  tracing::error!("{DENY_ERROR}");
+}
+
 
 /// Check that the `log` backend emits log messages.
 #[cfg(feature = "log")]
@@ -121,6 +396,230 @@ fn trace_emits_output() {
   );
 }
 
+/// Check that `tracing` output carries the span named after the test
+/// emitting it.
+#[cfg(feature = "trace")]
+#[test]
+fn trace_output_contains_test_span() {
+  let output = run_target("emit_trace_info", &[]);
+  let line = output
+    .lines()
+    .find(|line| line.contains(TRACE_INFO))
+    .unwrap_or_else(|| panic!("expected trace output not found in stderr:\n{output}"));
+  assert!(
+    line.contains("output::emit_trace_info"),
+    "test span not found in output:\n{line}",
+  );
+}
+
+/// Check that span field directives in the default filter are honored.
+#[cfg(feature = "trace")]
+#[test]
+fn trace_span_field_directive() {
+  let output = run_target("emit_trace_span_field_directive", &[("RUST_LOG", "")]);
+  assert!(
+    !output.contains(TRACE_OUTSIDE),
+    "unexpected trace output:\n{output}"
+  );
+  assert!(
+    output.contains(TRACE_INSIDE),
+    "missing trace output:\n{output}"
+  );
+}
+
+/// Check that span field directives are ignored by the `log` backend,
+/// without affecting the remaining directives.
+#[cfg(all(feature = "log", not(feature = "trace")))]
+#[test]
+fn log_span_field_directive() {
+  let output = run_target("emit_log_span_field_directive", &[("RUST_LOG", "")]);
+  assert!(
+    !output.contains("invalid logging spec"),
+    "unexpected warning:\n{output}"
+  );
+  assert!(output.contains(LOG_INFO), "missing log output:\n{output}");
+  assert!(
+    !output.contains(LOG_DEBUG),
+    "unexpected log output:\n{output}"
+  );
+}
+
+/// Find the line of `output` containing `marker`.
+fn find_line<'output>(output: &'output str, marker: &str) -> &'output str {
+  output
+    .lines()
+    .find(|line| line.contains(marker))
+    .unwrap_or_else(|| panic!("`{marker}` not found in stderr:\n{output}"))
+}
+
+/// Check that the `log` backend emits records in the configured format,
+/// unless overridden via `RUST_LOG_FORMAT`, adhering to the documented
+/// JSON schema.
+#[cfg(feature = "log")]
+#[test]
+fn log_format() {
+  let output = run_target("emit_log_format", &[]);
+  let line = find_line(&output, FORMAT_INFO);
+  let test = r#""test":"output::emit_log_format","level":"INFO","target":"output","module_path":"output","file":"tests/output.rs","line":"#;
+  let fields = format!(r#","fields":{{"message":"{FORMAT_INFO}"}},"spans":["#);
+  assert!(line.starts_with(r#"{"timestamp":""#), "{line}");
+  assert!(line.contains(test), "{line}");
+  assert!(line.contains(&fields), "{line}");
+
+  let output = run_target("emit_log_format", &[("RUST_LOG_FORMAT", "pretty")]);
+  assert!(output.contains(FORMAT_INFO), "{output}");
+  assert!(output.contains("    at tests/output.rs:"), "{output}");
+}
+
+/// Check that the `tracing` backend emits events in the configured
+/// format, unless overridden via `RUST_LOG_FORMAT`, adhering to the
+/// documented JSON schema.
+#[cfg(feature = "trace")]
+#[test]
+fn trace_format() {
+  let output = run_target("emit_trace_format", &[]);
+  let line = find_line(&output, FORMAT_INFO);
+  let test = r#""test":"output::emit_trace_format","level":"INFO","target":"output","module_path":"output","file":"tests/output.rs","line":"#;
+  let fields = format!(r#","fields":{{"message":"{FORMAT_INFO}","answer":"42"}},"#);
+  let spans = r#","spans":[{"name":"output::emit_trace_format","fields":{}},{"name":"outer","fields":{"id":"7"}}]}"#;
+  assert!(line.starts_with(r#"{"timestamp":""#), "{line}");
+  assert!(line.contains(test), "{line}");
+  assert!(line.contains(&fields), "{line}");
+  assert!(line.ends_with(spans), "{line}");
+
+  let output = run_target("emit_trace_format", &[("RUST_LOG_FORMAT", "pretty")]);
+  assert!(output.contains(FORMAT_INFO), "{output}");
+  assert!(output.contains("    at tests/output.rs:"), "{output}");
+}
+
+/// Check that the `log` backend writes output to the test's log file in
+/// addition to the regular output, without any coloring and keeping
+/// the file of the previous run around.
+#[cfg(feature = "log")]
+#[test]
+fn log_file_output() {
+  let path = test_file("test-logs", "emit_log_file.log");
+  let _output = run_target("emit_log_file", &[]);
+  let output = run_target("emit_log_file", &[]);
+  assert!(output.contains(FILE_INFO), "{output}");
+  assert_eq!(output.contains('\x1b'), cfg!(feature = "color"), "{output}");
+
+  let contents = fs::read_to_string(&path).unwrap();
+  assert!(contents.contains(FILE_INFO), "{contents}");
+  assert!(!contents.contains('\x1b'), "{contents}");
+  let contents = fs::read_to_string(path.with_extension("log.1")).unwrap();
+  assert!(contents.contains(FILE_INFO), "{contents}");
+
+  let () = fs::remove_file(&path).unwrap();
+  let output = run_target("emit_log_file", &[("RUST_LOG_FILES", "off")]);
+  assert!(output.contains(FILE_INFO), "{output}");
+  assert!(!path.exists());
+
+  // The level is padded the same way, regardless of coloring.
+  let _output = run_target("emit_log_file", &[("RUST_LOG_FORMAT", "full")]);
+  let contents = fs::read_to_string(&path).unwrap();
+  assert!(contents.contains("Z  INFO "), "{contents}");
+}
+
+/// Check that the `tracing` backend writes output to the test's log
+/// file in addition to the regular output, without any coloring and
+/// keeping the file of the previous run around.
+#[cfg(feature = "trace")]
+#[test]
+fn trace_file_output() {
+  let path = test_file("test-logs", "emit_trace_file.log");
+  let _output = run_target("emit_trace_file", &[]);
+  let output = run_target("emit_trace_file", &[]);
+  assert!(output.contains(FILE_INFO), "{output}");
+  assert_eq!(output.contains('\x1b'), cfg!(feature = "color"), "{output}");
+
+  let contents = fs::read_to_string(&path).unwrap();
+  assert!(contents.contains(FILE_INFO), "{contents}");
+  assert!(!contents.contains('\x1b'), "{contents}");
+  let contents = fs::read_to_string(path.with_extension("log.1")).unwrap();
+  assert!(contents.contains(FILE_INFO), "{contents}");
+
+  let () = fs::remove_file(&path).unwrap();
+  let output = run_target("emit_trace_file", &[("RUST_LOG_FILES", "off")]);
+  assert!(output.contains(FILE_INFO), "{output}");
+  assert!(!path.exists());
+}
+
+/// Check that output from threads not associated with any test is
+/// emitted using the default configuration and not written to the log
+/// file of the test that happens to run first.
+#[cfg(feature = "log")]
+#[test]
+fn log_file_thread_output() {
+  let path = test_file("test-logs", "emit_log_file_thread.log");
+  let output = run_target("emit_log_file_thread", &[]);
+  assert!(output.contains(FILE_THREAD), "{output}");
+
+  let contents = fs::read_to_string(&path).unwrap();
+  assert!(contents.contains(FILE_INFO), "{contents}");
+  assert!(!contents.contains(FILE_THREAD), "{contents}");
+}
+
+/// Check that output from threads not associated with any test is
+/// emitted using the default configuration and not written to the log
+/// file of the test that happens to run first.
+#[cfg(feature = "trace")]
+#[test]
+fn trace_file_thread_output() {
+  let path = test_file("test-logs", "emit_trace_file_thread.log");
+  let output = run_target("emit_trace_file_thread", &[]);
+  assert!(output.contains(FILE_THREAD), "{output}");
+
+  let contents = fs::read_to_string(&path).unwrap();
+  assert!(contents.contains(FILE_INFO), "{contents}");
+  assert!(!contents.contains(FILE_THREAD), "{contents}");
+}
+
+/// Check that the spans and events of a test are written to its Chrome
+/// trace event file.
+#[cfg(feature = "chrome-trace")]
+#[test]
+fn trace_chrome() {
+  let path = test_file("test-traces", "emit_trace_chrome.json");
+  let _output = run_target("emit_trace_chrome", &[]);
+
+  let contents = fs::read_to_string(path).unwrap();
+  assert!(contents.starts_with('['), "{contents}");
+  assert!(contents.ends_with(']'), "{contents}");
+  let test = r#""name":"output::emit_trace_chrome","ph":"B""#;
+  assert!(contents.contains(test), "{contents}");
+  let span = r#""args":{"id":"7"},"cat":"output","name":"instrumented","ph":"B""#;
+  assert!(contents.contains(span), "{contents}");
+  assert!(contents.contains(CHROME_INFO), "{contents}");
+}
+
+/// Check that the span timings of a test are written to its folded
+/// stack file.
+#[cfg(feature = "flame")]
+#[test]
+fn trace_flame() {
+  let path = test_file("test-flames", "emit_trace_flame.folded");
+  let output = run_target("emit_trace_flame", &[]);
+  assert!(output.contains(FLAME_INFO), "{output}");
+
+  let contents = fs::read_to_string(path).unwrap();
+  let mut stacks = 0;
+  for line in contents.lines() {
+    let (stack, nanos) = line.rsplit_once(' ').unwrap();
+    let _nanos = nanos.parse::<u64>().unwrap();
+    assert!(
+      stack.contains("emit_trace_flame; output::output::emit_trace_flame:"),
+      "{line}"
+    );
+    if stack.contains("; output::instrumented:tests/output.rs:") {
+      stacks += 1;
+    }
+  }
+  // Time spent in a span is recorded once it is exited, i.e., once for
+  // each call of the instrumented function.
+  assert_eq!(stacks, 2, "{contents}");
+}
+
 /// Verify that log levels are honored as expected.
 #[cfg(feature = "log")]
 #[test]
@@ -157,3 +656,166 @@ fn trace_level_filtering() {
   assert!(output.contains(TRACE_INFO_LEVELS), "missing info");
   assert!(output.contains(TRACE_DEBUG), "missing debug");
 }
+
+/// Check that the `env` policy governs how `RUST_LOG` is combined with a
+/// test's filter by the `log` backend.
+#[cfg(feature = "log")]
+#[test]
+fn log_env_policy() {
+  let env = [("RUST_LOG", "special=debug")];
+  let output = run_target("emit_log_env_merge", &env);
+  assert!(output.contains(ENV_INFO), "missing info:\n{output}");
+  assert!(output.contains(ENV_DEBUG), "missing debug:\n{output}");
+
+  let output = run_target("emit_log_env_ignore", &env);
+  assert!(output.contains(ENV_INFO), "missing info:\n{output}");
+  assert!(!output.contains(ENV_DEBUG), "unexpected debug:\n{output}");
+}
+
+/// Check that the `env` policy governs how `RUST_LOG` is combined with a
+/// test's filter by the `tracing` backend.
+#[cfg(feature = "trace")]
+#[test]
+fn trace_env_policy() {
+  let env = [("RUST_LOG", "special=debug")];
+  let output = run_target("emit_trace_env_merge", &env);
+  assert!(output.contains(ENV_INFO), "missing info:\n{output}");
+  assert!(output.contains(ENV_DEBUG), "missing debug:\n{output}");
+
+  let output = run_target("emit_trace_env_ignore", &env);
+  assert!(output.contains(ENV_INFO), "missing info:\n{output}");
+  assert!(!output.contains(ENV_DEBUG), "unexpected debug:\n{output}");
+}
+
+/// Check that a test configured with a custom environment variable reads
+/// its filter from that variable instead of `RUST_LOG` with the `log`
+/// backend.
+#[cfg(feature = "log")]
+#[test]
+fn log_env_var() {
+  let output = run_target("emit_log_env_var", &[("RUST_LOG", "special=debug")]);
+  assert!(output.contains(ENV_INFO), "missing info:\n{output}");
+  assert!(!output.contains(ENV_DEBUG), "unexpected debug:\n{output}");
+
+  let output = run_target("emit_log_env_var", &[("OUTPUT_TEST_LOG", "special=debug")]);
+  assert!(!output.contains(ENV_INFO), "unexpected info:\n{output}");
+  assert!(output.contains(ENV_DEBUG), "missing debug:\n{output}");
+}
+
+/// Check that a test configured with a custom environment variable reads
+/// its filter from that variable instead of `RUST_LOG` with the
+/// `tracing` backend.
+#[cfg(feature = "trace")]
+#[test]
+fn trace_env_var() {
+  let output = run_target("emit_trace_env_var", &[("RUST_LOG", "special=debug")]);
+  assert!(output.contains(ENV_INFO), "missing info:\n{output}");
+  assert!(!output.contains(ENV_DEBUG), "unexpected debug:\n{output}");
+
+  let output = run_target(
+    "emit_trace_env_var",
+    &[("OUTPUT_TEST_LOG", "special=debug")],
+  );
+  assert!(!output.contains(ENV_INFO), "unexpected info:\n{output}");
+  assert!(output.contains(ENV_DEBUG), "missing debug:\n{output}");
+}
+
+/// Check that span events are read from a custom environment variable,
+/// if configured.
+#[cfg(feature = "trace")]
+#[test]
+fn trace_span_events_env_var() {
+  let close = format!("{TRACE_SPAN}: output: close");
+  let new = format!("{TRACE_SPAN}: output: new");
+  let env = [("RUST_LOG_SPAN_EVENTS", "new")];
+  let output = run_target("emit_trace_span_events_env_var", &env);
+  assert!(output.contains(&close), "missing close event:\n{output}");
+  assert!(!output.contains(&new), "unexpected new event:\n{output}");
+
+  let env = [("OUTPUT_SPAN_EVENTS", "new")];
+  let output = run_target("emit_trace_span_events_env_var", &env);
+  assert!(
+    !output.contains(&close),
+    "unexpected close event:\n{output}"
+  );
+  assert!(output.contains(&new), "missing new event:\n{output}");
+}
+
+/// Check that the span events configured for a test are emitted, unless
+/// overridden by `RUST_LOG_SPAN_EVENTS`.
+#[cfg(feature = "trace")]
+#[test]
+fn trace_span_events() {
+  let output = run_target("emit_trace_span_events", &[]);
+  let close = format!("{TRACE_SPAN}: output: close");
+  let new = format!("{TRACE_SPAN}: output: new");
+  assert!(output.contains(&close), "missing close event:\n{output}");
+  assert!(!output.contains(&new), "unexpected new event:\n{output}");
+
+  let output = run_target("emit_trace_span_events", &[("RUST_LOG_SPAN_EVENTS", "new")]);
+  assert!(
+    !output.contains(&close),
+    "unexpected close event:\n{output}"
+  );
+  assert!(output.contains(&new), "missing new event:\n{output}");
+}
+
+/// Check that output recorded by the `log` backend is emitted only if
+/// the test fails.
+#[cfg(feature = "log")]
+#[test]
+fn log_capture_on_failure() {
+  let (success, output) = run_capture_target("emit_log_captured", "pass");
+  assert!(success, "subprocess failed:\n{output}");
+  assert!(
+    !output.contains(CAPTURE_TRACE),
+    "unexpected output:\n{output}"
+  );
+
+  for outcome in ["panic", "err"] {
+    let (success, output) = run_capture_target("emit_log_captured", outcome);
+    assert!(!success, "subprocess succeeded:\n{output}");
+    assert!(output.contains(CAPTURE_TRACE), "missing output:\n{output}");
+  }
+}
+
+/// Check that output recorded by the `tracing` backend is emitted only
+/// if the test fails.
+#[cfg(feature = "trace")]
+#[test]
+fn trace_capture_on_failure() {
+  let (success, output) = run_capture_target("emit_trace_captured", "pass");
+  assert!(success, "subprocess failed:\n{output}");
+  assert!(
+    !output.contains(CAPTURE_TRACE),
+    "unexpected output:\n{output}"
+  );
+
+  for outcome in ["panic", "err"] {
+    let (success, output) = run_capture_target("emit_trace_captured", outcome);
+    assert!(!success, "subprocess succeeded:\n{output}");
+    assert!(output.contains(CAPTURE_TRACE), "missing output:\n{output}");
+  }
+}
+
+/// Check that a test emitting a denied event via the `log` backend fails,
+/// even if its output is turned off via `RUST_LOG`.
+#[cfg(feature = "log")]
+#[test]
+fn log_deny_level_with_output_off() {
+  let (success, output) = run_target_status("emit_log_denied", &[("RUST_LOG", "off")]);
+  assert!(!success, "subprocess succeeded:\n{output}");
+  assert!(output.contains("at or above level"), "{output}");
+  assert!(output.contains(DENY_ERROR), "{output}");
+}
+
+/// Check that a test emitting a denied event via the `tracing` backend
+/// fails, even if its output is turned off via `RUST_LOG`.
+#[cfg(feature = "trace")]
+#[test]
+fn trace_deny_level_with_output_off() {
+  let (success, output) = run_target_status("emit_trace_denied", &[("RUST_LOG", "off")]);
+  assert!(!success, "subprocess succeeded:\n{output}");
+  assert!(output.contains("at or above level"), "{output}");
+  assert!(output.contains(DENY_ERROR), "{output}");
+}